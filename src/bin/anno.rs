@@ -5,39 +5,129 @@ mod game;
 
 resource_types!{Money, Wood, Tools, Stone}
 
-building_types!{House, Church, Lumberjack, Fisherman, Market}
+building_types!{House, Church, Lumberjack, Fisherman, Market, Quarry, School, FireStation, Workshop, Garden, TradePost, Warehouse}
 
-fn update_house(resources: &mut game::Resources, i: i32) {
-    if i % 100 != 0 { 
-        return 
+use game::behavior::{BuildingBehavior, Context};
+
+// houses pay taxes, a bit more when there is a market within reach
+#[derive(Clone)]
+struct PayTaxes;
+
+impl BuildingBehavior for PayTaxes {
+    fn update(&mut self, ctx: &mut Context) {
+        let market = ctx.spatial.nearest(ctx.building.entrance(), BuildingType::Market as usize);
+        let bonus = match market {
+            Some((_, d)) if d <= 12 => 1,
+            _ => 0,
+        };
+        ctx.produced.update(Resource::Money, 1 + bonus);
     }
-    resources.update(Resource::Money, 1);
 }
 
-fn update_lumberjack(resources: &mut game::Resources, i: i32) {
-    if i % 100 != 0 { 
-        return 
+// fishermen only catch anything close to the water
+#[derive(Clone)]
+struct Fishing;
+
+impl BuildingBehavior for Fishing {
+    fn update(&mut self, ctx: &mut Context) {
+        if ctx.near_terrain(game::Terrain::Water, 2) {
+            ctx.produced.update(Resource::Money, 2);
+        }
     }
-    resources.update(Resource::Wood, 1);
 }
 
-fn update_empty(_: &mut game::Resources, _: i32) {}
-
 fn main() {
     use Resource::*;
     use BuildingType::*;
-    use game::Game;
+    use game::{Exit, Game};
     use game::Coord;
-    use game::building::Building;
+    use game::building::{Building, Footprint};
+    use game::ledger::Category::Taxes;
+    use game::behavior::{Idle, Produce};
+    use game::events::{EventKind, Events};
+    use game::scenario::Scenario;
+    use game::script::Scripted;
+    use game::tech::Requirement;
+    use game::prices::Prices;
+    use game::trade::{Partner, Trade};
+    use game::walker::WalkerKind;
+
+    let church = Scripted::new(include_str!("../../scripts/anno/church.rhai"), RESOURCE_NAMES, BUILDING_NAMES)
+        .expect("church script does not compile");
 
     // we can also macro this
     // macro everything and make a DSL :)
     let buildings = [
-        Building::new_type(House, Coord::new(2,2), resources![(Money, 10), (Wood, 3)], ORANGE, update_house),
-        Building::new_type(Church, Coord::new(1,2), resources![(Money, 100), (Wood, 5), (Tools, 3)], YELLOW, update_empty),
-        Building::new_type(Lumberjack, Coord::new(2,2), resources![(Money, 10), (Tools, 2)], GREEN, update_lumberjack),
-        Building::new_type(Fisherman, Coord::new(2,2), resources![(Money, 10), (Wood, 3)], BLUE, update_empty),
-        Building::new_type(Market, Coord::new(3,4), resources![(Money, 200), (Wood, 5)], RED, update_empty),
+        Building::new_type(House, Coord::new(2,2), resources![(Money, 10), (Wood, 3)], ORANGE, PayTaxes)
+            .with_income(Taxes)
+            .with_risk(1, 1)
+            .with_residents(4)
+            .with_upgrade(10, 6)
+            .with_upgrade(20, 8),
+        Building::new_type(Church, Coord::new(1,2), resources![(Money, 100), (Wood, 5), (Tools, 3)], YELLOW, church)
+            .with_requirement(Requirement::Population(20))
+            .with_prevention(EventKind::Plague, 10)
+            .with_desirability(8, 6)
+            .with_income(Taxes)
+            .with_risk(0, 1)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(1),
+        Building::new_type(Lumberjack, Coord::new(2,2), resources![(Money, 10), (Tools, 2)], GREEN, Produce(resources![(Wood, 1)]))
+            .with_period(50)
+            .with_desirability(-4, 3)
+            .with_risk(2, 1)
+            .with_upkeep(resources![(Money, 1)])
+            .with_workers(2),
+        Building::new_type(Fisherman, Coord::new(2,2), resources![(Money, 10), (Wood, 3)], BLUE, Fishing)
+            .with_desirability(-2, 2)
+            .with_risk(1, 1)
+            .with_upkeep(resources![(Money, 1)])
+            .with_workers(2),
+        Building::new_type(Market, Footprint::from_rows(&["###", "###", "##.", "E#."]), resources![(Money, 200), (Wood, 5)], RED, Idle)
+            .with_requirement(Requirement::Population(60))
+            .with_desirability(5, 5)
+            .with_risk(1, 2)
+            .with_upkeep(resources![(Money, 5), (Wood, 1)])
+            .with_workers(4),
+        Building::new_type(Quarry, Coord::new(2,2), resources![(Money, 20), (Tools, 2)], GREY, Produce(resources![(Stone, 1)]))
+            .with_requirement(Requirement::research(School, 40))
+            .with_period(50)
+            .with_desirability(-8, 5)
+            .with_risk(0, 2)
+            .with_upkeep(resources![(Money, 1)])
+            .with_workers(2),
+        Building::new_type(School, Coord::new(2,2), resources![(Money, 50), (Wood, 5)], LIGHT_BLUE, Idle)
+            .with_requirement(Requirement::built(Church))
+            .with_research(2)
+            .with_desirability(4, 4)
+            .with_risk(1, 1)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(2),
+        Building::new_type(FireStation, Coord::new(2,2), resources![(Money, 40), (Wood, 2)], LIGHT_RED, Idle)
+            .with_prevention(EventKind::Fire, 10)
+            .with_walker(WalkerKind::Prefect)
+            .with_risk(0, 1)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(2),
+        Building::new_type(Workshop, Coord::new(2,2), resources![(Money, 40), (Tools, 1)], LIGHT_YELLOW, Idle)
+            .with_walker(WalkerKind::Engineer)
+            .with_desirability(-3, 3)
+            .with_risk(1, 0)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(2),
+        Building::new_type(Garden, Coord::new(2,2), resources![(Money, 30)], LIGHT_GREEN, Idle)
+            .with_desirability(6, 4)
+            .with_upkeep(resources![(Money, 1)]),
+        Building::new_type(TradePost, Coord::new(3,2), resources![(Money, 150), (Wood, 10)], LIGHT_AMBER, Idle)
+            .with_requirement(Requirement::Population(40))
+            .with_trade_post()
+            .with_risk(1, 1)
+            .with_upkeep(resources![(Money, 3)])
+            .with_workers(2),
+        Building::new_type(Warehouse, Coord::new(2,3), resources![(Money, 60), (Wood, 5)], LIGHT_SEPIA, Idle)
+            .with_storage(resources![(Wood, 100), (Tools, 50), (Stone, 100)])
+            .with_risk(1, 1)
+            .with_upkeep(resources![(Money, 1)]),
     ];
 
     // NOTE: buildings should be in sync with enum BuildingType
@@ -48,12 +138,42 @@ fn main() {
     const MAP_HEIGHT: i32 = 45;
 
     let road_cost = resources![(Money, 2)];
-    let mut map = game::make_map(Coord::new(MAP_WIDTH, MAP_HEIGHT));
-    let mut game = Game::new(&buildings, MAP_WIDTH, MAP_HEIGHT, NumResources, road_cost, &mut map);
+    let map = game::make_map(Coord::new(MAP_WIDTH, MAP_HEIGHT)).with_river(MAP_WIDTH / 3);
+    let scenario = Scenario::new("New shores", RESOURCE_NAMES, BUILDING_NAMES)
+        .with_population(100)
+        .with_stockpile(Stone, 50)
+        .with_building_by(Church, 6000)
+        .with_never_bankrupt(Money);
+    let events = Events::new(1701)
+        .with_chance(EventKind::Fire, 50)
+        .with_chance(EventKind::Flood, 40)
+        .with_chance(EventKind::Plague, 30)
+        .with_chance(EventKind::Storm, 50)
+        .with_chance(EventKind::Windfall, 60);
+    // ships come to the trade post from across the sea
+    let trade = Trade::new(Money, NumResources)
+        .with_partner(Partner::new("Venice", 600, NumResources)
+            .with_sell(Wood, 4)
+            .with_sell(Tools, 8)
+            .with_buy(Stone, 6))
+        .with_partner(Partner::new("Flanders", 900, NumResources)
+            .with_sell(Stone, 10)
+            .with_buy(Wood, 2)
+            .with_buy(Tools, 5));
+    let mut game = Game::new(&buildings, NumResources, road_cost, map)
+        .with_names(BUILDING_NAMES)
+        .with_scenario(scenario)
+        .with_events(events)
+        .with_bridge_cost(resources![(Money, 10), (Wood, 2)])
+        .with_trade(trade)
+        .with_prices(Prices::new(Money, resources![(Wood, 3), (Tools, 6), (Stone, 8)]));
     game.resources.set(Money, 1000);
     game.resources.set(Wood, 50);
     game.resources.set(Tools, 10);
 
-    let tcod = game::get_tcod(&game);
-    game::do_loop(tcod, &mut game);
+    let mut tcod = game::get_tcod(&game);
+    // past the victory or defeat screen the city is played on without goals
+    while game::do_loop(&mut tcod, &mut game) == Exit::Continue {
+        game.end_scenario();
+    }
 }
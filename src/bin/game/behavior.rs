@@ -0,0 +1,93 @@
+use crate::game::Coord;
+use crate::game::Map;
+use crate::game::Resources;
+use crate::game::Stock;
+use crate::game::Terrain;
+use crate::game::building::Building;
+use crate::game::entity::Entity;
+use crate::game::spatial::SpatialIndex;
+use crate::game::ledger::Ledger;
+
+// everything a building can see when it updates; what it puts in
+// produced is scaled by staffing and added to the stock afterwards
+pub struct Context<'c> {
+    pub entity:     Entity,
+    pub building:   &'c Building,
+    pub map:        &'c Map,
+    // other buildings within NEIGHBOUR_RADIUS of this one
+    pub neighbours: Vec<&'c Building>,
+    // for looking further than the neighbours
+    pub spatial:    &'c SpatialIndex,
+    pub stock:      &'c Stock,
+    pub ledger:     &'c Ledger,
+    pub tick:       i32,
+    pub produced:   Resources,
+    // terrain changes asked for, applied afterwards to tiles nothing is built on
+    pub edits:      Vec<(Coord, Terrain)>,
+    // what went wrong, for the event log
+    pub errors:     Vec<String>,
+}
+
+impl<'c> Context<'c> {
+    // whether any tile within radius of the building has the given terrain
+    pub fn near_terrain(&self, terrain: Terrain, radius: i32) -> bool {
+        self.map.near_terrain(self.building, terrain, radius)
+    }
+
+    // how many neighbouring buildings are of the given type
+    pub fn count_neighbours<T>(&self, btype: T) -> usize
+    where T: std::convert::Into<usize>
+    {
+        let btype = btype.into();
+        self.neighbours.iter().filter(|b| b.btype == btype).count()
+    }
+
+    pub fn set_terrain(&mut self, c: Coord, terrain: Terrain) {
+        self.edits.push((c, terrain));
+    }
+}
+
+// what a placed building does every time its period comes around;
+// each placed building gets its own copy, so it can keep state.
+// whatever on_place and on_demolish produce is not scaled by staffing
+pub trait BuildingBehavior: BehaviorClone {
+    fn update(&mut self, ctx: &mut Context);
+
+    fn on_place(&mut self, _: &mut Context) {}
+
+    fn on_demolish(&mut self, _: &mut Context) {}
+}
+
+pub trait BehaviorClone {
+    fn clone_box(&self) -> Box<dyn BuildingBehavior>;
+}
+
+impl<T> BehaviorClone for T where T: 'static + BuildingBehavior + Clone {
+    fn clone_box(&self) -> Box<dyn BuildingBehavior> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn BuildingBehavior> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// does nothing
+#[derive(Clone)]
+pub struct Idle;
+
+impl BuildingBehavior for Idle {
+    fn update(&mut self, _: &mut Context) {}
+}
+
+// produces the same resources every period
+#[derive(Clone)]
+pub struct Produce(pub Resources);
+
+impl BuildingBehavior for Produce {
+    fn update(&mut self, ctx: &mut Context) {
+        ctx.produced = self.0.clone();
+    }
+}
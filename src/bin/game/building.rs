@@ -3,28 +3,245 @@ use tcod::colors::Color;
 
 use crate::game::Coord;
 use crate::game::Resources;
+use crate::game::Terrain;
+use crate::game::ledger::Category;
+use crate::game::behavior::BuildingBehavior;
+use crate::game::events::EventKind;
+use crate::game::tech::Requirement;
+use crate::game::walker::WalkerKind;
+
+// the shape of a building as a mask of tiles within its bounding box,
+// with one of the occupied tiles marked as the entrance
+#[derive(Clone)]
+pub struct Footprint {
+    pub dimxy:    Coord,
+    pub entrance: Coord,
+    mask:         Vec<bool>,
+}
+
+impl Footprint {
+    // a full rectangle with the entrance at the bottom-left
+    pub fn rect(dimxy: Coord) -> Self {
+        Footprint{
+            dimxy: dimxy,
+            entrance: Coord::new(0, dimxy.y - 1),
+            mask: vec![true; (dimxy.x * dimxy.y) as usize],
+        }
+    }
+
+    // reads a footprint from rows of text: '#' is an occupied tile,
+    // 'E' is the entrance (also occupied), anything else is left open
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let dimx = rows.iter().map(|r| r.len()).max().unwrap_or(0) as i32;
+        let dimy = rows.len() as i32;
+        let mut mask = vec![false; (dimx * dimy) as usize];
+        let mut entrance = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let i = y * dimx as usize + x;
+                match c {
+                    '#' => mask[i] = true,
+                    'E' => {
+                        mask[i] = true;
+                        entrance = Some(Coord::new(x as i32, y as i32));
+                    },
+                    _ => {},
+                }
+            }
+        }
+        let entrance = entrance.expect("footprint needs an entrance tile 'E'");
+        Footprint{ dimxy: Coord::new(dimx, dimy), entrance, mask }
+    }
+
+    pub fn contains(&self, c: Coord) -> bool {
+        if c.x < 0 || c.y < 0 || c.x >= self.dimxy.x || c.y >= self.dimxy.y {
+            return false
+        }
+        self.mask[(c.y * self.dimxy.x + c.x) as usize]
+    }
+
+    // all occupied tiles relative to the topleft, row by row
+    pub fn tiles(&self) -> Vec<Coord> {
+        let mut tiles = vec![];
+        for y in 0..self.dimxy.y {
+            for x in 0..self.dimxy.x {
+                let c = Coord::new(x, y);
+                if self.contains(c) {
+                    tiles.push(c);
+                }
+            }
+        }
+        tiles
+    }
+
+    // turns the footprint 90 degrees clockwise
+    pub fn rotate(&self) -> Self {
+        let Coord{x: w, y: h} = self.dimxy;
+        let mut mask = vec![false; (w * h) as usize];
+        for c in self.tiles() {
+            // (x,y) in a w*h box ends up at (h-1-y, x) in a h*w box
+            mask[(c.x * h + h - 1 - c.y) as usize] = true;
+        }
+        Footprint{
+            dimxy: Coord::new(h, w),
+            entrance: Coord::new(h - 1 - self.entrance.y, self.entrance.x),
+            mask: mask,
+        }
+    }
+}
+
+impl From<Coord> for Footprint {
+    fn from(dimxy: Coord) -> Self {
+        Footprint::rect(dimxy)
+    }
+}
 
 #[derive(Clone)]
 pub struct Building {
-    pub btype:   usize,
-    pub topleft: Coord,
-    pub dimxy:   Coord,
-    pub cost:    Resources,
+    pub btype:     usize,
+    pub topleft:   Coord,
+    pub footprint: Footprint,
+    pub cost:      Resources,
+    // the kind of ground every tile of the building needs
+    pub terrain:   Terrain,
+    // how what the building produces shows up in the ledger
+    pub income:    Category,
+    // paid every upkeep period; buildings that cannot pay stop working
+    pub upkeep:    Resources,
+    // people living here, available to work in buildings on the same roads
+    pub residents: i32,
+    // people needed to run at full output
+    pub workers:   i32,
+    // research points gathered every period at full staffing
+    pub research:  i32,
+    // all of these have to be met before the type can be built
+    pub requires:  Vec<Requirement>,
+    // events made less likely within a radius around the entrance
+    pub prevents:  Vec<(EventKind, i32)>,
+    // how much fire risk and damage build up every tick
+    pub fire_risk:   i32,
+    pub damage_risk: i32,
+    // sent out along the roads to look after the buildings around
+    pub walker:    Option<WalkerKind>,
+    // how much the building adds to the desirability of the land around it,
+    // falling off with distance to nothing past the radius; negative for
+    // buildings nobody wants to live next to
+    pub desirability: (i32, i32),
+    // levels a house moves up to once its land is desirable enough,
+    // as the desirability needed and the residents it then houses
+    pub upgrades:  Vec<(i32, i32)>,
+    // ships from trade partners only come while one of these is working
+    pub trade_post: bool,
+    // how much of every good the building stores; producers
+    // stall when no storage around them has room
+    pub storage:   Resources,
     color:   Color,
-    pub update_fn: fn(&mut Resources, i32),
+    // runs once every period ticks; placed buildings each get their own copy
+    pub behavior:  Box<dyn BuildingBehavior>,
+    pub period:    i32,
+}
+
+// what changes about a placed building while the game runs
+pub struct State {
+    pub active:  bool,
+    pub staffed: i32,
+    // ticks left out of use, or producing double, after an event
+    pub disabled: i32,
+    pub boosted:  i32,
+    // the building burns or collapses once either reaches RISK_THRESHOLD
+    pub fire:     i32,
+    pub damage:   i32,
+    // how many upgrades the building has gone through
+    pub level:    usize,
+    // the last output did not all find room in storage
+    pub stalled:  bool,
+    // ticks since the building was placed
+    timer:       i32,
+    // output owed from partial staffing, paid out once it adds up
+    carry:       Resources,
+}
+
+impl State {
+    pub fn new(b: &Building) -> Self {
+        State{
+            active:  true,
+            staffed: 0,
+            disabled: 0,
+            boosted:  0,
+            fire:     0,
+            damage:   0,
+            level:    0,
+            stalled:  false,
+            timer:   0,
+            carry:   Resources(vec![0; b.cost.len()]),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.timer += 1;
+        self.disabled = (self.disabled - 1).max(0);
+        self.boosted = (self.boosted - 1).max(0);
+    }
+
+    // paid its upkeep and not disabled by an event
+    pub fn working(&self) -> bool {
+        self.active && self.disabled == 0
+    }
+
+    // whether something that happens every period ticks is due this tick
+    pub fn due(&self, period: i32) -> bool {
+        self.timer % period == 0
+    }
+
+    // scales what the building produced by how well it is staffed
+    pub fn staffed_output(&mut self, b: &Building, produced: &Resources) -> Resources {
+        if b.workers == 0 {
+            return produced.clone()
+        }
+        let mut out = produced.clone();
+        for (r, &v) in produced.0.iter().enumerate() {
+            let owed = v * self.staffed + self.carry.0[r];
+            out.0[r] = owed / b.workers;
+            self.carry.0[r] = owed % b.workers;
+        }
+        out
+    }
+
+    pub fn staffed_research(&self, b: &Building) -> i32 {
+        if b.workers == 0 {
+            return b.research
+        }
+        b.research * self.staffed / b.workers
+    }
 }
 
 impl Building {
-    pub fn new_type<T>(btype: T, dimxy: Coord, cost: Resources, color: Color, f: fn(&mut Resources, i32)) -> Self 
-    where T: std::convert::Into<usize> 
+    pub fn new_type<T, F, B>(btype: T, footprint: F, cost: Resources, color: Color, behavior: B) -> Self 
+    where T: std::convert::Into<usize>, F: std::convert::Into<Footprint>, B: BuildingBehavior + 'static
     {
         Building{ 
             btype: btype.into(), 
             topleft: Coord::new( 0, 0 ), 
-            dimxy: dimxy, 
-            cost: cost, 
+            footprint: footprint.into(), 
+            cost: cost.clone(), 
+            terrain: Terrain::Ground, 
+            income: Category::Production, 
+            upkeep: Resources(vec![0; cost.len()]), 
+            residents: 0, 
+            workers: 0, 
+            research: 0, 
+            requires: vec![], 
+            prevents: vec![], 
+            fire_risk: 0, 
+            damage_risk: 0, 
+            walker: None, 
+            desirability: (0, 0), 
+            upgrades: vec![], 
+            trade_post: false, 
+            storage: Resources(vec![0; cost.len()]), 
             color: color, 
-            update_fn:f, 
+            behavior: Box::new(behavior), 
+            period: 100, 
         }    
     }
 
@@ -34,47 +251,165 @@ impl Building {
         t
     }
 
+    pub fn with_income(mut self, income: Category) -> Self {
+        self.income = income;
+        self
+    }
+
+    pub fn with_upkeep(mut self, upkeep: Resources) -> Self {
+        self.upkeep = upkeep;
+        self
+    }
+
+    pub fn with_period(mut self, period: i32) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn with_residents(mut self, residents: i32) -> Self {
+        self.residents = residents;
+        self
+    }
+
+    pub fn with_workers(mut self, workers: i32) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn with_research(mut self, research: i32) -> Self {
+        self.research = research;
+        self
+    }
+
+    pub fn with_requirement(mut self, requirement: Requirement) -> Self {
+        self.requires.push(requirement);
+        self
+    }
+
+    pub fn with_prevention(mut self, kind: EventKind, radius: i32) -> Self {
+        self.prevents.push((kind, radius));
+        self
+    }
+
+    pub fn with_risk(mut self, fire: i32, damage: i32) -> Self {
+        self.fire_risk = fire;
+        self.damage_risk = damage;
+        self
+    }
+
+    pub fn with_walker(mut self, walker: WalkerKind) -> Self {
+        self.walker = Some(walker);
+        self
+    }
+
+    pub fn with_desirability(mut self, value: i32, radius: i32) -> Self {
+        self.desirability = (value, radius);
+        self
+    }
+
+    // upgrades have to be added from the lowest desirability up
+    pub fn with_upgrade(mut self, desirability: i32, residents: i32) -> Self {
+        self.upgrades.push((desirability, residents));
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Resources) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn with_trade_post(mut self) -> Self {
+        self.trade_post = true;
+        self
+    }
+
+    // the level a house reaches on land this desirable
+    pub fn level_for(&self, desirability: i32) -> usize {
+        self.upgrades.iter().take_while(|&&(d, _)| desirability >= d).count()
+    }
+
+    pub fn residents_at(&self, level: usize) -> i32 {
+        match level {
+            0 => self.residents,
+            l => self.upgrades.get(l - 1).map_or(self.residents, |&(_, r)| r),
+        }
+    }
+
+    // what the building adds to the desirability of the tile at c,
+    // going by the distance to the nearest edge of its bounding box
+    pub fn desirability_at(&self, c: Coord) -> i32 {
+        let (value, radius) = self.desirability;
+        let bottomright = Coord::new(self.topleft.x + self.footprint.dimxy.x - 1, self.topleft.y + self.footprint.dimxy.y - 1);
+        let dx = (self.topleft.x - c.x).max(c.x - bottomright.x).max(0);
+        let dy = (self.topleft.y - c.y).max(c.y - bottomright.y).max(0);
+        let d = dx.max(dy);
+        if d > radius {
+            return 0
+        }
+        value * (radius + 1 - d) / (radius + 1)
+    }
+
+    // manhattan distance between the entrances of two buildings
+    pub fn distance(&self, other: &Building) -> i32 {
+        let (a, b) = (self.entrance(), other.entrance());
+        (a.x - b.x).abs() + (a.y - b.y).abs()
+    }
+
+    pub fn rotate(&mut self) {
+        self.footprint = self.footprint.rotate();
+    }
+
+    // all tiles covered by the building on the map
+    pub fn tiles(&self) -> Vec<Coord> {
+        self.footprint.tiles().iter().map(|c| Coord::new(self.topleft.x + c.x, self.topleft.y + c.y)).collect()
+    }
+
+    pub fn entrance(&self) -> Coord {
+        Coord::new(self.topleft.x + self.footprint.entrance.x, self.topleft.y + self.footprint.entrance.y)
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
     pub fn draw(&self, con: &mut dyn Console) {
-        con.set_default_foreground(self.color);
-        if self.dimxy.x == 1 {
-            // church
-            con.put_char(self.topleft.x, self.topleft.y, 197 as char, BackgroundFlag::None);
-            con.put_char(self.topleft.x, self.topleft.y+1, 179 as char, BackgroundFlag::None);
+        self.draw_in(con, self.color);
+    }
+
+    pub fn draw_in(&self, con: &mut dyn Console, color: Color) {
+        con.set_default_foreground(color);
+        let fp = &self.footprint;
+        if fp.dimxy.x == 1 || fp.dimxy.y == 1 {
+            // church: a cross with a pole
+            for (i, c) in self.tiles().iter().enumerate() {
+                let glyph: u8 = if i == 0 { 197 } else if fp.dimxy.x == 1 { 179 } else { 196 };
+                con.put_char(c.x, c.y, glyph as char, BackgroundFlag::None);
+            }
             return
         }
-        // others
-        for y in self.topleft.y..self.topleft.y + self.dimxy.y {
-            for x in self.topleft.x..self.topleft.x + self.dimxy.x {
-                if x == self.topleft.x {
-                    if y == self.topleft.y {
-                        con.put_char(x, y, 201 as char, BackgroundFlag::None);
-                        continue
-                    }
-                    if y == self.topleft.y + self.dimxy.y - 1{
-                        con.put_char(x, y, 200 as char, BackgroundFlag::None);
-                        continue
-                    }
-                    con.put_char(x, y, 186 as char, BackgroundFlag::None);
-                    continue
-                }
-                if x == self.topleft.x + self.dimxy.x - 1{
-                    if y == self.topleft.y {
-                        con.put_char(x, y, 187 as char, BackgroundFlag::None);
-                        continue
-                    }
-                    if y == self.topleft.y + self.dimxy.y - 1{
-                        con.put_char(x, y, 188 as char, BackgroundFlag::None);
-                        continue
-                    }
-                    con.put_char(x, y, 186 as char, BackgroundFlag::None);
-                    continue
-                }
-                if y == self.topleft.y || y == self.topleft.y + self.dimxy.y - 1 {
-                    con.put_char(x, y, 205 as char, BackgroundFlag::None);
-                    continue
-                }
-                con.put_char(x, y, 178 as char, BackgroundFlag::None);
-            }
+        // others: walls on the edges of the mask, filled inside
+        for c in fp.tiles() {
+            let up    = fp.contains(Coord::new(c.x, c.y-1));
+            let down  = fp.contains(Coord::new(c.x, c.y+1));
+            let left  = fp.contains(Coord::new(c.x-1, c.y));
+            let right = fp.contains(Coord::new(c.x+1, c.y));
+            let glyph: u8 = match (up, down, left, right) {
+                (false, _, false, _) => 201,
+                (false, _, _, false) => 187,
+                (_, false, false, _) => 200,
+                (_, false, _, false) => 188,
+                (_, _, false, _) | (_, _, _, false) => 186,
+                (false, _, _, _) | (_, false, _, _) => 205,
+                _ => 178,
+            };
+            con.put_char(self.topleft.x + c.x, self.topleft.y + c.y, glyph as char, BackgroundFlag::None);
         }
     }
+
+    // marks the entrance, used when previewing placement
+    pub fn draw_entrance(&self, con: &mut dyn Console) {
+        let e = self.entrance();
+        con.set_default_foreground(tcod::colors::WHITE);
+        con.put_char(e.x, e.y, 254 as char, BackgroundFlag::None);
+    }
 }
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::game::scenario::Scenario;
+
+// one map of the campaign, and the building types winning it unlocks
+#[derive(Clone)]
+pub struct Mission {
+    pub scenario: Scenario,
+    unlocks:      Vec<usize>,
+}
+
+// scenarios played one after the other; the building types unlocked along
+// the way carry over to every later mission. progress is kept in a plain
+// text file holding the number of missions completed
+pub struct Campaign {
+    missions:  Vec<Mission>,
+    // building types available from the first mission on
+    start:     Vec<usize>,
+    save:      PathBuf,
+    completed: usize,
+}
+
+impl Campaign {
+    pub fn new<P>(save: P) -> Self
+    where P: Into<PathBuf>
+    {
+        Campaign{
+            missions:  vec![],
+            start:     vec![],
+            save:      save.into(),
+            completed: 0,
+        }
+    }
+
+    pub fn with_start<T>(mut self, btypes: &[T]) -> Self
+    where T: std::convert::Into<usize> + Copy
+    {
+        self.start.extend(btypes.iter().map(|&b| b.into()));
+        self
+    }
+
+    pub fn with_mission<T>(mut self, scenario: Scenario, unlocks: &[T]) -> Self
+    where T: std::convert::Into<usize> + Copy
+    {
+        self.missions.push(Mission{ scenario, unlocks: unlocks.iter().map(|&b| b.into()).collect() });
+        self
+    }
+
+    // a missing save file means a fresh campaign
+    pub fn load(&mut self) -> io::Result<()> {
+        let text = match fs::read_to_string(&self.save) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let completed = text.trim().parse::<usize>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.completed = completed.min(self.missions.len());
+        Ok(())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::write(&self.save, format!("{}\n", self.completed))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed >= self.missions.len()
+    }
+
+    // the mission to play next; once the campaign is finished
+    // the last one can be replayed
+    pub fn current(&self) -> Option<&Mission> {
+        let i = self.completed.min(self.missions.len().saturating_sub(1));
+        self.missions.get(i)
+    }
+
+    // building types unlocked by the missions completed so far
+    pub fn available(&self) -> Vec<usize> {
+        let mut available = self.start.clone();
+        for m in self.missions.iter().take(self.completed) {
+            available.extend(m.unlocks.iter().cloned());
+        }
+        available
+    }
+
+    pub fn complete(&mut self) {
+        if !self.is_finished() {
+            self.completed += 1;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+// how long a single simulation tick takes at normal speed
+const TICK: Duration = Duration::from_millis(50);
+// after a long stall we drop time instead of running this many ticks in a row
+const MAX_TICKS_PER_FRAME: u32 = 20;
+
+// turns wall clock time into a number of fixed simulation ticks,
+// so the simulation runs the same no matter how fast we render
+pub struct Clock {
+    speed:  u32,
+    paused: bool,
+    last:   Instant,
+    lag:    Duration,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock{
+            speed:  1,
+            paused: false,
+            last:   Instant::now(),
+            lag:    Duration::new(0, 0),
+        }
+    }
+
+    // starts counting from now, dropping whatever time went by before
+    pub fn reset(&mut self) {
+        self.last = Instant::now();
+        self.lag = Duration::new(0, 0);
+    }
+
+    // the number of ticks to simulate since the last call
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        if self.paused {
+            return 0
+        }
+        self.lag += elapsed * self.speed;
+        let mut ticks = 0;
+        while self.lag >= TICK {
+            self.lag -= TICK;
+            ticks += 1;
+            if ticks == MAX_TICKS_PER_FRAME {
+                self.lag = Duration::new(0, 0);
+                break
+            }
+        }
+        ticks
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+}
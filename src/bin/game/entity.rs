@@ -0,0 +1,77 @@
+// a stable handle to something placed on the map; ids are never reused
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity(u32);
+
+impl Entity {
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+// hands out new entities
+pub struct Entities {
+    next: u32,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Entities{ next: 0 }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        let e = Entity(self.next);
+        self.next += 1;
+        e
+    }
+}
+
+// one kind of data for any number of entities, indexed by entity id
+// so lookups are O(1) and iteration goes in the order entities were made
+pub struct Components<T> {
+    items: Vec<Option<T>>,
+}
+
+impl<T> Components<T> {
+    pub fn new() -> Self {
+        Components{ items: vec![] }
+    }
+
+    pub fn insert(&mut self, e: Entity, t: T) {
+        if e.index() >= self.items.len() {
+            self.items.resize_with(e.index() + 1, || None);
+        }
+        self.items[e.index()] = Some(t);
+    }
+
+    pub fn remove(&mut self, e: Entity) -> Option<T> {
+        self.items.get_mut(e.index()).and_then(|t| t.take())
+    }
+
+    pub fn get(&self, e: Entity) -> Option<&T> {
+        self.items.get(e.index()).and_then(|t| t.as_ref())
+    }
+
+    pub fn get_mut(&mut self, e: Entity) -> Option<&mut T> {
+        self.items.get_mut(e.index()).and_then(|t| t.as_mut())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Entity, &T)> {
+        self.items.iter().enumerate().filter_map(|(i, t)| t.as_ref().map(|t| (Entity(i as u32), t)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(Entity, &mut T)> {
+        self.items.iter_mut().enumerate().filter_map(|(i, t)| t.as_mut().map(|t| (Entity(i as u32), t)))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item=&T> {
+        self.items.iter().filter_map(|t| t.as_ref())
+    }
+
+    pub fn entities(&self) -> Vec<Entity> {
+        self.iter().map(|(e, _)| e).collect()
+    }
+}
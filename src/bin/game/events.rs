@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+use tcod::random::{Algo, Rng};
+
+// buildings are checked for events once every this many ticks
+pub const EVENT_PERIOD: i32 = 200;
+// a building protected against an event is hit this many times less often
+pub const PREVENTION: i32 = 4;
+// how many lines the event log keeps
+const LOG_SIZE: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    // burns the building down
+    Fire,
+    // disables a building close to the water
+    Flood,
+    // disables a house
+    Plague,
+    // disables any building
+    Storm,
+    // doubles what a building produces for a while
+    Windfall,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Fire     => "fire",
+            EventKind::Flood    => "flood",
+            EventKind::Plague   => "plague",
+            EventKind::Storm    => "storm",
+            EventKind::Windfall => "windfall",
+        }
+    }
+
+    // how many ticks the building stays disabled or boosted
+    pub fn duration(&self) -> i32 {
+        match self {
+            EventKind::Fire     => 0,
+            EventKind::Flood    => 400,
+            EventKind::Plague   => 600,
+            EventKind::Storm    => 200,
+            EventKind::Windfall => 300,
+        }
+    }
+}
+
+// the events that can happen in a game and how likely they are; the same
+// seed and the same play give the same events
+pub struct Events {
+    rng:     Rng,
+    // chance per thousand, per check, that an event hits the building it picked
+    chances: Vec<(EventKind, i32)>,
+    log:     VecDeque<String>,
+}
+
+impl Events {
+    pub fn new(seed: u32) -> Self {
+        Events{
+            rng:     Rng::new_with_seed(Algo::MT, seed),
+            chances: vec![],
+            log:     VecDeque::new(),
+        }
+    }
+
+    pub fn with_chance(mut self, kind: EventKind, per_mille: i32) -> Self {
+        self.chances.push((kind, per_mille));
+        self
+    }
+
+    pub fn chances(&self) -> Vec<(EventKind, i32)> {
+        self.chances.clone()
+    }
+
+    // one of n things, at random
+    pub fn pick(&mut self, n: usize) -> usize {
+        self.rng.get_int(0, n as i32 - 1) as usize
+    }
+
+    pub fn roll(&mut self, per_mille: i32) -> bool {
+        self.rng.get_int(0, 999) < per_mille
+    }
+
+    pub fn record(&mut self, tick: i32, text: String) {
+        self.log.push_back(format!("{:>6} {}", tick, text));
+        while self.log.len() > LOG_SIZE {
+            self.log.pop_front();
+        }
+    }
+
+    // oldest first
+    pub fn log(&self) -> impl Iterator<Item=&String> {
+        self.log.iter()
+    }
+}
@@ -0,0 +1,75 @@
+use crate::game::Coord;
+
+// a rectangle of per-tile values stored row by row in one vec,
+// so lookups are an index computation instead of a hash
+#[derive(Clone)]
+pub struct Grid<T> {
+    dimxy: Coord,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(dimxy: Coord, fill: T) -> Self {
+        Grid::from_fn(dimxy, |_| fill.clone())
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn<F>(dimxy: Coord, mut f: F) -> Self
+    where F: FnMut(Coord) -> T
+    {
+        assert!(dimxy.x >= 0 && dimxy.y >= 0, "grid of negative size");
+        let mut cells = Vec::with_capacity((dimxy.x * dimxy.y) as usize);
+        for y in 0..dimxy.y {
+            for x in 0..dimxy.x {
+                cells.push(f(Coord::new(x, y)));
+            }
+        }
+        Grid{ dimxy, cells }
+    }
+
+    pub fn dimxy(&self) -> Coord {
+        self.dimxy
+    }
+
+    pub fn in_bounds(&self, c: Coord) -> bool {
+        c.x >= 0 && c.y >= 0 && c.x < self.dimxy.x && c.y < self.dimxy.y
+    }
+
+    fn index(&self, c: Coord) -> Option<usize> {
+        if self.in_bounds(c) {
+            Some((c.y * self.dimxy.x + c.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, c: Coord) -> Option<&T> {
+        self.index(c).map(move |i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, c: Coord) -> Option<&mut T> {
+        match self.index(c) {
+            Some(i) => Some(&mut self.cells[i]),
+            None => None,
+        }
+    }
+
+    // returns false when c is off the grid
+    pub fn set(&mut self, c: Coord, t: T) -> bool {
+        match self.get_mut(c) {
+            Some(cell) => { *cell = t; true },
+            None => false,
+        }
+    }
+
+    // one slice per row, top to bottom
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.cells.chunks(std::cmp::max(self.dimxy.x, 1) as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Coord, &T)> {
+        let w = self.dimxy.x;
+        self.cells.iter().enumerate().map(move |(i, t)| (Coord::new(i as i32 % w, i as i32 / w), t))
+    }
+}
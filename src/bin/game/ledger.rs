@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use crate::game::Resources;
+use crate::game::Stock;
+
+// how many ticks of transactions are kept for the rolling statistics
+pub const WINDOW: i32 = 1000;
+// how often stock levels are sampled for the graphs, and how many samples are kept
+const SAMPLE_EVERY: i32 = 20;
+pub const HISTORY: usize = 60;
+
+// where a change in resources came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Category {
+    Building,
+    Road,
+    Production,
+    Taxes,
+    Upkeep,
+    Trade,
+    // goods destroyed along with the building holding them
+    Losses,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [Category::Building, Category::Road, Category::Production, Category::Taxes, Category::Upkeep, Category::Trade, Category::Losses];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Building   => "building",
+            Category::Road       => "road",
+            Category::Production => "production",
+            Category::Taxes      => "taxes",
+            Category::Upkeep     => "upkeep",
+            Category::Trade      => "trade",
+            Category::Losses     => "losses",
+        }
+    }
+}
+
+// a single income (positive amount) or expense (negative amount)
+#[derive(Clone, Copy, Debug)]
+pub struct Transaction {
+    pub tick:     i32,
+    pub category: Category,
+    pub resource: usize,
+    pub amount:   i32,
+}
+
+pub struct Ledger {
+    recent:  VecDeque<Transaction>,
+    // sampled stock levels per resource, oldest first
+    history: Vec<VecDeque<i32>>,
+}
+
+impl Ledger {
+    pub fn new(num_resources: usize) -> Self {
+        Ledger{
+            recent:  VecDeque::new(),
+            history: vec![VecDeque::new(); num_resources],
+        }
+    }
+
+    // records every nonzero entry of a resources vector as its own transaction
+    pub fn record(&mut self, tick: i32, category: Category, amounts: &Resources) {
+        for (r, &amount) in amounts.0.iter().enumerate() {
+            if amount != 0 {
+                self.recent.push_back(Transaction{ tick, category, resource: r, amount });
+            }
+        }
+        self.prune(tick);
+    }
+
+    fn prune(&mut self, tick: i32) {
+        while let Some(t) = self.recent.front() {
+            if t.tick > tick - WINDOW {
+                break
+            }
+            self.recent.pop_front();
+        }
+    }
+
+    // keeps track of stock levels over time, call once every tick
+    pub fn sample(&mut self, tick: i32, stock: &Stock) {
+        self.prune(tick);
+        if tick % SAMPLE_EVERY != 0 {
+            return
+        }
+        for (r, h) in self.history.iter_mut().enumerate() {
+            h.push_back(stock.get(r));
+            if h.len() > HISTORY {
+                h.pop_front();
+            }
+        }
+    }
+
+    // sum of all transactions of a resource in a category over the window
+    pub fn total(&self, resource: usize, category: Category) -> i32 {
+        self.recent.iter().filter(|t| t.resource == resource && t.category == category).map(|t| t.amount).sum()
+    }
+
+    // sum of all transactions of a resource over the window
+    pub fn net(&self, resource: usize) -> i32 {
+        self.recent.iter().filter(|t| t.resource == resource).map(|t| t.amount).sum()
+    }
+
+    // how much of a resource went into building, roads and upkeep over
+    // the window, leaving out what was traded away
+    pub fn spent(&self, resource: usize) -> i32 {
+        self.recent.iter().filter(|t| t.resource == resource && t.category != Category::Trade && t.category != Category::Losses && t.amount < 0)
+            .map(|t| -t.amount).sum()
+    }
+
+    pub fn history(&self, resource: usize) -> &VecDeque<i32> {
+        &self.history[resource]
+    }
+}
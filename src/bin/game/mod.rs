@@ -1,10 +1,44 @@
 use tcod::console::*;
 use tcod::colors::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-use building::Building;
+use behavior::{BuildingBehavior, Context};
+use building::{Building, State};
+use clock::Clock;
+use entity::{Components, Entities, Entity};
+use events::{EventKind, Events};
+use grid::Grid;
+use overlay::{Overlay, Scale};
+use prices::Prices;
+use spatial::SpatialIndex;
+use storage::{Setting, Store};
+use tech::Requirement;
+use trade::{Partner, Trade};
+use walker::{Walker, WalkerKind};
+use ledger::{Category, Ledger};
+use scenario::{Outcome, Progress, Scenario, Status};
+pub use resources::{Resources, Stock, Insufficient};
+pub mod behavior;
 pub mod building;
+#[allow(dead_code)] // not every game plays a campaign
+pub mod campaign;
+pub mod clock;
+pub mod entity;
+pub mod events;
+pub mod grid;
+pub mod ledger;
+pub mod overlay;
 pub mod path;
+pub mod prices;
+pub mod resources;
+pub mod scenario;
+pub mod script;
+pub mod spatial;
+pub mod storage;
+pub mod tech;
+pub mod trade;
+pub mod walker;
 
 const SCREEN_WIDTH: i32 = 80;
 const SCREEN_HEIGHT: i32 = 50;
@@ -15,9 +49,19 @@ const COLOR_DARK_GROUND: Color = Color {
     g: 50,
     b: 150,
 };
+const COLOR_WATER: Color = Color { r: 0, g: 60, b: 200 };
 
 const LIMIT_FPS: i32 = 20; // 20 frames-per-second maximum
 
+// buildings pay their upkeep once every this many ticks
+const UPKEEP_PERIOD: i32 = 100;
+// how far a building looks for neighbours when it updates
+const NEIGHBOUR_RADIUS: i32 = 6;
+// the most water tiles a single bridge spans
+const BRIDGE_SPAN: i32 = 4;
+// how much more a bridge tile costs than a road tile, by default
+const BRIDGE_COST: i32 = 5;
+
 pub struct Tcod {
     root: Root,
     con: Offscreen,
@@ -39,25 +83,41 @@ pub fn get_tcod(game: &Game) -> Tcod {
     Tcod { root, con }
 }
 
-pub fn do_loop(mut tcod: Tcod, game: &mut Game) {
-    let mut i = 0;
+// how the player left the game loop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    Quit,
+    // past the victory or defeat screen
+    Continue,
+}
+
+pub fn do_loop(tcod: &mut Tcod, game: &mut Game) -> Exit {
+    // the time spent setting up the game and opening the window is not played
+    game.clock.reset();
     while !tcod.root.window_closed() {
         tcod.con.clear();
-        game.render_all(&mut tcod);
+        game.update_layers();
+        game.render_all(tcod);
         tcod.root.flush();
 
-        game.update_buildings(i);
+        // the simulation runs in fixed ticks, however long the frame took,
+        // and stops once the scenario is decided
+        for _ in 0..game.clock.advance() {
+            if game.outcome() != Outcome::Playing {
+                break
+            }
+            game.update_buildings();
+        }
 
         // handle keys and exit game if needed
-        let exit = handle_keys(&mut tcod, game);
-        if exit {
-            break;
+        if let Some(exit) = handle_keys(tcod, game) {
+            return exit
         }
-        i += 1;
     }
+    Exit::Quit
 }
 
-fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> bool { //, player: &mut Object) -> bool {
+fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> Option<Exit> { //, player: &mut Object) -> bool {
     use tcod::input::Key;
     use tcod::input::KeyCode::*;
     use tcod::input::Mouse;
@@ -76,52 +136,96 @@ fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> bool { //, player: &mut Obje
         game.mousexy.y = mouse.cy as i32;
     }
 
-    if mouse.lbutton {
-        if game.mousexy.y > game.mapxy.y {
+    // nothing left to do on the victory or defeat screen but leave
+    if game.outcome() != Outcome::Playing {
+        return match key.code {
+            Escape => Some(Exit::Quit),
+            Enter => Some(Exit::Continue),
+            _ => None,
+        }
+    }
+
+    // the trade screen takes the keys to set quotas, and hides the map
+    if let (true, Some(ref mut trade)) = (game.show_trade, game.trade.as_mut()) {
+        let r = trade.selected;
+        let have = game.resources.get(r);
+        match (key.code, key.text()) {
+            (Up, _) => trade.select(-1),
+            (Down, _) => trade.select(1),
+            (Left, _) => trade.change_import(r, -trade::QUOTA_STEP),
+            (Right, _) => trade.change_import(r, trade::QUOTA_STEP),
+            (Text, "-") => trade.change_export(r, -trade::QUOTA_STEP, have),
+            (Text, "+") | (Text, "=") => trade.change_export(r, trade::QUOTA_STEP, have),
+            (Text, "k") => trade.keep_all(r),
+            (Text, "t") => game.show_trade = false,
+            _ => return handle_screen_keys(game, key),
+        }
+        return None
+    }
+
+    // the stats screen hides the map, so nothing on it can be touched
+    if game.show_stats {
+        return handle_screen_keys(game, key)
+    }
+
+    if game.mousexy.y >= game.mapxy.y {
+        if mouse.lbutton {
             // selecting a building in the bottom of the screen
-            let x = (game.mousexy.x as usize - 1) / 4;
-            if x < game.buildingtypes.len() {
+            if let Some(x) = game.palette_at(game.mousexy).filter(|&x| game.is_available(x)) {
                 game.draw = Draw::Building(game.buildingtypes[x].btype);
+                game.rotation = 0;
+                game.drag = None;
             }
-        } else {
-            // drawing a building on the map
+        }
+    } else {
+        if mouse.lbutton {
+            // holding the button drags out a row of buildings or a road
+            if game.drag.is_none() {
+                game.drag = Some(game.mousexy);
+            }
+            let mousexy = game.mousexy;
+            // only follow the mouse where a road can go
+            let follow = match game.draw {
+                Draw::Road(ref waypoints) => match waypoints.last() {
+                    // roads start on land, bridges only lead off it
+                    None => game.map.get(&mousexy).map_or(false, |t| t.terrain == Terrain::Ground || t.road),
+                    Some(&last) => last != mousexy && game.route(last, mousexy).is_some(),
+                },
+                _ => false,
+            };
+            if let (Draw::Road(ref mut waypoints), true) = (&mut game.draw, follow) {
+                waypoints.push(mousexy);
+            }
+        }
+        if mouse.lbutton_pressed {
+            // releasing the button builds whatever was dragged
             match game.draw {
                 Draw::Building(u) => {
-                    let newhouse = game.get_building_to_build(u);
-                    if !game.is_blocked(&newhouse) && game.can_pay_for(&newhouse) {
-                        game.add(newhouse);
+                    for newhouse in game.get_buildings_to_build(u) {
+                        // we simply stop placing once money runs out
+                        let _ = game.place(newhouse);
                     }
                 },
-                Draw::Road(r) => {
-                    match r {
-                        Some(road_start) => {
-                            match path::find_route(&game.map, road_start, game.mousexy) {
-                                Some(path) => {
-                                    if game.resources.covers(&game.road_cost) {
-                                        // TODO check whole road cost
-                                    }
-                                    for c in path.iter() {
-                                        if game.map.get(c).unwrap().road {
-                                            continue
-                                        }
-                                        game.add_road(*c);
-                                    }
-                                    game.draw = Draw::Road(None);
-                                },
-                                None => {},
-                            }
-                        },
-                        None => {
-                            game.draw = Draw::Road(Some(game.mousexy));   
-                        },
+                Draw::Road(_) => {
+                    // builds as much of the road as we can pay for
+                    for c in game.get_affordable_road() {
+                        let _ = game.place_road(c);
                     }
+                    game.draw = Draw::Road(vec![]);
+                },
+                Draw::None => {
+                    // clicking on a building selects it
+                    game.selected = game.building_at(game.mousexy);
                 },
-                Draw::None => {},
             }
+            game.drag = None;
         }
     }
     if mouse.rbutton {
         game.draw = Draw::None;
+        game.drag = None;
+        game.selected = None;
+        game.bridging = false;
     }
 
     match key {
@@ -134,8 +238,45 @@ fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> bool { //, player: &mut Obje
             let fullscreen = tcod.root.is_fullscreen();
             tcod.root.set_fullscreen(!fullscreen);
         },
-        Key { code: Escape, .. } => return true, // exit game
-        Key { code: Text, .. } => if key.text() == "r" { game.draw = Draw::Road(None) },
+        Key { code: Delete, .. } => {
+            if let Some(e) = game.selected.take() {
+                game.demolish(e);
+            } else {
+                // we simply leave the rubble if we cannot pay to clear it
+                let _ = game.clear_rubble(game.mousexy);
+            }
+        },
+        Key { code: Text, .. } => match key.text() {
+            "r" => {
+                game.draw = Draw::Road(vec![]);
+                game.bridging = false;
+            },
+            // roads that may cross short stretches of water
+            "b" => {
+                game.draw = Draw::Road(vec![]);
+                game.bridging = true;
+            },
+            // rotate the building being placed clockwise
+            "e" => game.rotation = (game.rotation + 1) % 4,
+            "g" => game.show_goals = !game.show_goals,
+            "l" => game.show_log = !game.show_log,
+            "t" => game.show_trade = !game.show_trade && game.trade.is_some(),
+            // pick a good of the selected storage and change what it does with it
+            "[" | "]" | "a" => {
+                if let Some(store) = game.selected.and_then(|e| game.stores.get_mut(e)) {
+                    match key.text() {
+                        "[" => store.select(-1),
+                        "]" => store.select(1),
+                        _ => {
+                            let r = store.selected;
+                            store.cycle_setting(r);
+                        },
+                    }
+                }
+            },
+            "o" => game.overlay = game.next_overlay(),
+            _ => return handle_screen_keys(game, key),
+        },
 
         // movement keys
         /*
@@ -145,10 +286,27 @@ fn handle_keys(tcod: &mut Tcod, game: &mut Game) -> bool { //, player: &mut Obje
         Key { code: Right, .. } => player.move_by(1, 0, game),
         */
 
-        _ => {}
+        _ => return handle_screen_keys(game, key),
     };
 
-    false
+    None
+}
+
+// the keys that work on every screen: leaving, the clock and the stats screen
+fn handle_screen_keys(game: &mut Game, key: tcod::input::Key) -> Option<Exit> {
+    use tcod::input::KeyCode::*;
+
+    match (key.code, key.text()) {
+        (Escape, _) => return Some(Exit::Quit), // exit game
+        (Spacebar, _) => game.clock.toggle_pause(),
+        (Text, "s") => game.show_stats = !game.show_stats,
+        // simulation speed
+        (Text, "1") => game.clock.set_speed(1),
+        (Text, "2") => game.clock.set_speed(2),
+        (Text, "3") => game.clock.set_speed(4),
+        _ => {},
+    }
+    None
 }
 
 #[derive(PartialOrd,Ord,Clone,Copy,PartialEq,Eq,Hash)]
@@ -179,14 +337,18 @@ macro_rules! resources {
     };
 }
 
-// declares the types of resources in the game
+// declares the types of resources in the game,
+// and their names in the same order for scripts
 macro_rules! resource_types {
-    ($($body:tt)*) => {
+    ($($name:ident),*) => {
         as_item! {
             #[derive(Copy,Clone)]
-            enum Resource { $($body)* , NumResources }
+            enum Resource { $($name),* , NumResources }
         }
 
+        #[allow(dead_code)]
+        const RESOURCE_NAMES: &[&str] = &[$(stringify!($name)),*];
+
         impl Into<usize> for Resource {
             fn into(self) -> usize {
                 self as usize
@@ -195,14 +357,18 @@ macro_rules! resource_types {
     };
 }
 
-// declares the types of buildings in the game
+// declares the types of buildings in the game,
+// and their names in the same order for scripts
 macro_rules! building_types {
-    ($($body:tt)*) => {
+    ($($name:ident),*) => {
         as_item! {
             #[derive(Copy,Clone,PartialEq,Eq)]
-            enum BuildingType { $($body)* , NumBuildingTypes }
+            enum BuildingType { $($name),* , NumBuildingTypes }
         }
 
+        #[allow(dead_code)]
+        const BUILDING_NAMES: &[&str] = &[$(stringify!($name)),*];
+
         impl Into<usize> for BuildingType {
             fn into(self) -> usize {
                 self as usize
@@ -215,304 +381,2108 @@ macro_rules! as_item {
     ($i:item) => { $i };
 }
 
-#[derive(Clone)]
-pub struct Resources(pub Vec<(i32)>);
-
-impl Resources {
-    pub fn get<T>(&self, r: T) -> i32 
-    where T: std::convert::Into<usize> 
-    {
-        self.0[r.into()]
-    }
-
-    pub fn set<T>(&mut self, r: T, v: i32)
-    where T: std::convert::Into<usize> 
-    {
-        self.0[r.into()] = v;
-    }
-
-    pub fn update<T>(&mut self, r: T, v: i32)
-    where T: std::convert::Into<usize> 
-    {
-        let rusize = r.into();
-        self.0[rusize] = self.0[rusize] + v;
-    }
-
-    pub fn len(&self) -> usize {
-        self.0.len()
-    }
-
-    pub fn covers(&self, other: &Resources) -> bool {
-        self.0.iter().zip(other.0.iter()).filter(|(&x, &y)| x < y).collect::<Vec<(&i32, &i32)>>().len() == 0
-    }
+// reasons a building or road cannot go where it was asked to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    OutOfBounds,
+    Blocked,
+    WrongTerrain,
+    Unaffordable(Insufficient),
+    // the building type cannot be built yet
+    Locked,
+}
 
-    pub fn substract(&mut self, cost: &Resources) -> Resources {
-        // TODO inplace
-        Resources(self.0.iter().zip(cost.0.iter()).map(|(x, y)| x-y).collect::<Vec<i32>>())
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlacementError::OutOfBounds     => write!(f, "out of bounds"),
+            PlacementError::Blocked         => write!(f, "blocked"),
+            PlacementError::WrongTerrain    => write!(f, "wrong terrain"),
+            PlacementError::Unaffordable(i) => write!(f, "cannot afford: {}", i),
+            PlacementError::Locked          => write!(f, "not available yet"),
+        }
     }
 }
 
 #[derive(PartialEq, Eq)]
 pub enum Draw {
     Building(usize),
-    Road(Option<Coord>),
+    // the points the mouse dragged the road through
+    Road(Vec<Coord>),
     None,
 }
 
 pub struct Game<'a> {
-    map: Map<'a>, //HashMap<Coord, Tile>,
-    buildings: Vec<Building>,
+    map: Map,
+    entities: Entities,
+    // components of placed buildings
+    buildings: Components<Building>,
+    states: Components<State>,
+    behaviors: Components<Box<dyn BuildingBehavior>>,
+    stores: Components<Store>,
+    walkers: Vec<Walker>,
+    // the tiles of every building that burned or collapsed, until cleared
+    ruins: Vec<Vec<Coord>>,
+    spatial: SpatialIndex,
+    selected: Option<Entity>,
     buildingtypes: &'a [Building],
+    names: Vec<String>,
+    // which resources have to be kept in storage buildings
+    storable: Vec<bool>,
+    // which building types the campaign allows
+    available: Vec<bool>,
+    // which building types have met their requirements
+    unlocked: Vec<bool>,
+    // research points gathered at each building type
+    research: Vec<i32>,
     draw: Draw, //Option<usize>
-    pub resources: Resources,
+    rotation: usize,
+    drag: Option<Coord>,
+    pub resources: Stock,
+    pub ledger: Ledger,
+    // the resources a payment came up short on this tick
+    short: Vec<bool>,
     road_cost: Resources,
+    // per tile of road built over water
+    bridge_cost: Resources,
+    bridging: bool,
+    clock: Clock,
+    tick: i32,
+    scenario: Option<Scenario>,
+    events: Option<Events>,
+    trade: Option<Trade>,
+    prices: Option<Prices>,
+    show_stats: bool,
+    show_goals: bool,
+    show_log: bool,
+    show_trade: bool,
+    overlay: Overlay,
     mapxy: Coord,
     mousexy: Coord,
 }
 
 impl<'a> Game<'a> {
-    pub fn new<T>(buildings: &'a [Building], mapx: i32, mapy: i32, num_resources: T, road_cost: Resources, coords: &'a mut HashMap<Coord,Tile>) -> Self 
+    pub fn new<T>(buildings: &'a [Building], num_resources: T, road_cost: Resources, map: Map) -> Self 
     where T: std::convert::Into<usize> 
     {
+        let num_resources = num_resources.into();
+        // every cost has to name every resource in the game
+        assert_eq!(road_cost.len(), num_resources, "road cost of wrong length");
+        for b in buildings {
+            assert_eq!(b.cost.len(), num_resources, "building cost of wrong length");
+        }
+        let mapxy = map.dimxy();
+        // the layers worked out from the buildings, shown as overlays
+        let map = map
+            .with_layer(COVERAGE, Scale::Sequential{ max: 3, to: LIGHT_BLUE })
+            .with_layer(ROAD_NETWORKS, Scale::Categorical)
+            .with_layer(FIRE_RISK, Scale::Sequential{ max: 100, to: RED })
+            .with_layer(POPULATION, Scale::Sequential{ max: 10, to: ORANGE });
         let game = Game{ 
-            map: Map::new(coords), 
-            buildings: vec![], 
+            map: map, 
+            entities: Entities::new(),
+            buildings: Components::new(), 
+            states: Components::new(),
+            behaviors: Components::new(),
+            stores: Components::new(),
+            walkers: vec![],
+            ruins: vec![],
+            spatial: SpatialIndex::new(),
+            selected: None,
             buildingtypes: buildings, 
-            resources: Resources(vec![0; num_resources.into()]), 
+            names: (0..buildings.len()).map(|b| format!("#{}", b)).collect(),
+            storable: (0..num_resources).map(|r| buildings.iter().any(|b| b.storage.get(r) > 0)).collect(),
+            available: vec![true; buildings.len()],
+            unlocked: buildings.iter().map(|b| b.requires.is_empty()).collect(),
+            research: vec![0; buildings.len()],
+            resources: Stock::new(num_resources), 
+            ledger: Ledger::new(num_resources),
+            short: vec![false; num_resources],
+            bridge_cost: road_cost.times(BRIDGE_COST),
             road_cost: road_cost,
+            bridging: false,
+            clock: Clock::new(),
+            tick: 0,
+            scenario: None,
+            events: None,
+            trade: None,
+            prices: None,
+            show_stats: false,
+            show_goals: true,
+            show_log: true,
+            show_trade: false,
+            overlay: Overlay::Terrain,
             draw: Draw::None,
-            mapxy: Coord::new(mapx, mapy),
+            rotation: 0,
+            drag: None,
+            mapxy: mapxy,
             mousexy: Coord::new(0, 0),
         };
         game
     }
 
-    fn is_blocked(&self, newobj: &Building) -> bool {
-        let Coord{x, y} = newobj.topleft;
-        let Coord{x: dx, y: dy} = newobj.dimxy;
-        for x in x..(x+dx) {
-            for y in y..(y+dy) {
-                if x > self.mapxy.x || y > self.mapxy.y {
-                    return true
-                }
-                if self.map.get(&Coord::new(x,y)).unwrap().blocked {
-                    return true
-                }
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    // drops the scenario, to keep playing the map without goals
+    #[allow(dead_code)]
+    pub fn end_scenario(&mut self) {
+        self.scenario = None;
+    }
+
+    pub fn with_events(mut self, events: Events) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_trade(mut self, trade: Trade) -> Self {
+        self.trade = Some(trade);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_prices(mut self, prices: Prices) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    pub fn with_bridge_cost(mut self, cost: Resources) -> Self {
+        assert_eq!(cost.len(), self.road_cost.len(), "bridge cost of wrong length");
+        self.bridge_cost = cost;
+        self
+    }
+
+    // only these building types can be built
+    #[allow(dead_code)]
+    pub fn with_available(mut self, btypes: &[usize]) -> Self {
+        self.available = vec![false; self.buildingtypes.len()];
+        for &b in btypes {
+            if let Some(a) = self.available.get_mut(b) {
+                *a = true;
             }
         }
-        return false
+        self
     }
 
-    fn can_pay_for(&self, newobj: &Building) -> bool {
-        self.resources.covers(&newobj.cost)
+    // names of the building types, for the ui
+    pub fn with_names(mut self, names: &[&str]) -> Self {
+        self.names = names.iter().map(|n| n.to_string()).collect();
+        self
     }
 
-    fn add(&mut self, newobj: Building) {
-        self.block(&newobj);
-        self.resources = self.resources.substract(&newobj.cost);
-        self.buildings.push(newobj);
+    fn name_of(&self, btype: usize) -> String {
+        self.names.get(btype).cloned().unwrap_or_else(|| format!("#{}", btype))
     }
 
-    fn add_road(&mut self, coord: Coord) {
-        self.map.set(coord.clone(), Tile::road());
-        self.resources = self.resources.substract(&self.road_cost);
+    // whether the building type can be picked from the palette
+    pub fn is_available(&self, btype: usize) -> bool {
+        self.available.get(btype).cloned().unwrap_or(false)
+            && self.unlocked.get(btype).cloned().unwrap_or(false)
+    }
+
+    // a game without a scenario is played forever
+    pub fn outcome(&self) -> Outcome {
+        self.scenario.as_ref().map_or(Outcome::Playing, |s| s.outcome())
+    }
+
+    fn in_bounds(&self, c: Coord) -> bool {
+        self.map.tiles.in_bounds(c)
     }
 
-    fn block(&mut self, newobj: &Building) {
-        let Coord{x, y} = newobj.topleft;
-        let Coord{x: dx, y: dy} = newobj.dimxy;
-        for x in x..(x+dx) {
-            for y in y..(y+dy) {
-                self.map.set(Coord::new(x,y), Tile::wall());
+    // checks whether the building fits on the map where it is, ignoring cost
+    fn check_site(&self, newobj: &Building) -> Result<(), PlacementError> {
+        for c in newobj.tiles() {
+            if !self.in_bounds(c) {
+                return Err(PlacementError::OutOfBounds)
+            }
+        }
+        for c in newobj.tiles() {
+            let tile = self.map.get(&c).ok_or(PlacementError::OutOfBounds)?;
+            if tile.terrain != newobj.terrain {
+                return Err(PlacementError::WrongTerrain)
+            }
+            if tile.blocked || tile.rubble {
+                return Err(PlacementError::Blocked)
             }
         }
+        Ok(())
     }
 
-    fn get_building_to_build(&self, u: usize) -> Building {
-        self.buildingtypes[u].clone().new_token(self.mousexy)
+    fn check_available(&self, newobj: &Building) -> Result<(), PlacementError> {
+        if !self.is_available(newobj.btype) {
+            return Err(PlacementError::Locked)
+        }
+        Ok(())
+    }
+
+    pub fn check_placement(&self, newobj: &Building) -> Result<(), PlacementError> {
+        self.check_available(newobj)?;
+        self.check_site(newobj)?;
+        self.resources.check(&newobj.cost).map_err(PlacementError::Unaffordable)
     }
 
-    fn update_buildings(&mut self, i: i32) {
-        for o in &self.buildings {
-            (o.update_fn)(&mut self.resources, i);
+    // places the building if it may be built, fits and can be paid for
+    pub fn place(&mut self, newobj: Building) -> Result<Entity, PlacementError> {
+        self.check_available(&newobj)?;
+        self.check_site(&newobj)?;
+        self.resources.try_spend(&newobj.cost).map_err(PlacementError::Unaffordable)?;
+        self.ledger.record(self.tick, Category::Building, &newobj.cost.times(-1));
+        Ok(self.add(newobj))
+    }
+
+    fn check_road_site(&self, c: Coord) -> Result<(), PlacementError> {
+        if !self.in_bounds(c) {
+            return Err(PlacementError::OutOfBounds)
+        }
+        let tile = self.map.get(&c).ok_or(PlacementError::OutOfBounds)?;
+        let bridge = self.bridging && tile.terrain == Terrain::Water && self.map.bridgeable(c);
+        if tile.terrain != Terrain::Ground && !bridge {
+            return Err(PlacementError::WrongTerrain)
+        }
+        if tile.blocked || tile.rubble {
+            return Err(PlacementError::Blocked)
         }
+        Ok(())
     }
 
-    fn render_all(&self, tcod: &mut Tcod) {
-        // go through all tiles, and set their background color
-        for y in 0..self.mapxy.y {
-            for x in 0..self.mapxy.x {
-                let tile = self.map.get(&Coord::new(x,y)).unwrap();
-                if tile.blocked {
-                    tcod.con.set_char_background(x, y, COLOR_DARK_WALL, BackgroundFlag::Set);
-                } else {
-                    tcod.con.set_char_background(x, y, COLOR_DARK_GROUND, BackgroundFlag::Set);
-                }
-                if tile.road {
-                    tcod.con.set_default_foreground(GREY);
-                    tcod.con.put_char(x, y, 178 as char, BackgroundFlag::None);
+    // places a single road tile if it fits and can be paid for
+    pub fn place_road(&mut self, c: Coord) -> Result<(), PlacementError> {
+        self.check_road_site(c)?;
+        let cost = self.tile_cost(c);
+        self.resources.try_spend(&cost).map_err(PlacementError::Unaffordable)?;
+        self.ledger.record(self.tick, Category::Road, &cost.times(-1));
+        self.add_road(c);
+        Ok(())
+    }
+
+    // adds an already paid for building
+    fn add(&mut self, newobj: Building) -> Entity {
+        let e = self.entities.spawn();
+        self.block(e, &newobj);
+        self.states.insert(e, State::new(&newobj));
+        self.behaviors.insert(e, newobj.behavior.clone());
+        if !newobj.storage.is_zero() {
+            self.stores.insert(e, Store::new(&newobj.storage));
+        }
+        self.spatial.insert(e, newobj.btype, newobj.entrance());
+        self.buildings.insert(e, newobj);
+        self.update_desirability();
+        self.balance_stores();
+        // what buildings hand out when placed or torn down is not their income
+        if let Some(produced) = self.run_event(e, |behavior, ctx| behavior.on_place(ctx)) {
+            self.collect(e, &produced, Category::Production);
+        }
+        e
+    }
+
+    // removes a building and frees up its tiles, without refund
+    pub fn demolish(&mut self, e: Entity) {
+        if let Some(produced) = self.run_event(e, |behavior, ctx| behavior.on_demolish(ctx)) {
+            self.collect(e, &produced, Category::Production);
+        }
+        self.remove(e);
+    }
+
+    // takes a building off the map without running its behavior
+    fn remove(&mut self, e: Entity) {
+        if let Some(b) = self.buildings.remove(e) {
+            self.spatial.remove(e, b.entrance());
+            for c in b.tiles() {
+                if let Some(tile) = self.map.get_mut(&c) {
+                    tile.blocked = false;
+                    tile.occupant = None;
                 }
             }
         }
-        // draw all objects in the list
-        for object in &self.buildings {
-            object.draw(&mut tcod.con);
+        self.states.remove(e);
+        self.behaviors.remove(e);
+        // whatever was stored is lost with the building
+        if let Some(store) = self.stores.remove(e) {
+            let lost = Resources(store.amounts().0.iter().enumerate()
+                .map(|(r, &v)| self.resources.update(r, -v)).collect());
+            self.ledger.record(self.tick, Category::Losses, &lost);
         }
-    
-        // draw object types at the bottom of the screen
-        for (i, object) in self.buildingtypes.iter().enumerate() {
-            let mut o = object.clone();
-            o.topleft = Coord::new((i+1) as i32*4, self.mapxy.y + 1);
-            o.draw(&mut tcod.root);
+        self.update_desirability();
+        self.balance_stores();
+        if self.selected == Some(e) {
+            self.selected = None;
         }
-    
-        // currently selected buildingtype to draw
-        let mut costs = Resources(Vec::new());
-        if let Draw::Building(u) = self.draw {
-            let mut current = self.buildingtypes[u].clone();
-            // draw current at bottom-right as a reminder of current selection
-            current.topleft = Coord::new(self.mapxy.x-5, self.mapxy.y+1);
-            current.draw(&mut tcod.root);
-            current.topleft = self.mousexy.clone();
-            // draw current on the map as a preview
-            current.draw(&mut tcod.con);
-            costs = current.cost;
-        }
-        if let Draw::Road(r) = self.draw {
-            costs = self.road_cost.clone();
-            match r {
-                Some(road_start) => {
-                    match path::find_route(&self.map, road_start, self.mousexy) {
-                        Some(path) => {
-                            tcod.con.set_default_foreground(WHITE);
-                            for c in path.iter() {        
-                                tcod.con.put_char(c.x, c.y, 178 as char, BackgroundFlag::None);
-                            }
-                        },
-                        None => {},
+    }
+
+    // adds up what every building does to the desirability of the land around it
+    fn update_desirability(&mut self) {
+        let mut field = Grid::new(self.mapxy, 0);
+        for b in self.buildings.values() {
+            let (value, radius) = b.desirability;
+            if value == 0 {
+                continue
+            }
+            let Coord{x: w, y: h} = b.footprint.dimxy;
+            for y in b.topleft.y-radius..b.topleft.y+h+radius {
+                for x in b.topleft.x-radius..b.topleft.x+w+radius {
+                    let c = Coord::new(x, y);
+                    if let Some(d) = field.get_mut(c) {
+                        *d += b.desirability_at(c);
                     }
-                },
-                None => {
-                    tcod.con.set_default_foreground(WHITE);
-                    tcod.con.put_char(self.mousexy.x, self.mousexy.y, 178 as char, BackgroundFlag::None);
-                },
+                }
             }
         }
-    
-        // draw messages
-        tcod.con.set_default_foreground(WHITE); 
-        // TODO this should be more informed by the particular game
-        for (i, r) in (0..4).enumerate() {
-            if r >= self.resources.len() {
-                continue
+        if let Some(layer) = self.map.layer_mut(DESIRABILITY) {
+            *layer = field;
+        }
+    }
+
+    pub fn building(&self, e: Entity) -> Option<&Building> {
+        self.buildings.get(e)
+    }
+
+    pub fn building_at(&self, c: Coord) -> Option<Entity> {
+        self.map.get(&c).and_then(|t| t.occupant)
+    }
+
+    // buildings with their entrance within radius of c, optionally of one type
+    pub fn buildings_within(&self, c: Coord, radius: i32, btype: Option<usize>) -> Vec<Entity> {
+        self.spatial.within(c, radius, btype)
+    }
+
+    // tears down a building that burned or collapsed, leaving rubble;
+    // nothing is salvaged from it
+    fn ruin(&mut self, e: Entity) {
+        let tiles = match self.buildings.get(e) {
+            Some(b) => b.tiles(),
+            None => return,
+        };
+        self.remove(e);
+        for &c in tiles.iter() {
+            if let Some(tile) = self.map.get_mut(&c) {
+                tile.rubble = true;
             }
-            let c = match r {
-                0 => 15 as char,  // Money
-                1 => 240 as char, // Wood
-                2 => 209 as char, // Tools
-                3 => 219 as char, // Stone
-                _ => panic!(),
-            };
-            if self.draw == Draw::None || costs.get(r as usize) == 0 {
-                tcod.con.print_rect(i as i32 * 10 + 1, 1, 100, 0, format!("{}{}", c, self.resources.get(r as usize)));
-            } else {
-                tcod.con.print_rect(i as i32 * 10 + 1, 1, 100, 0, format!("{}{}({})", c, self.resources.get(r as usize), costs.get(r as usize)));
+        }
+        self.ruins.push(tiles);
+    }
+
+    // clears the whole ruin the tile belongs to, at the cost of
+    // a road tile for every tile of rubble
+    pub fn clear_rubble(&mut self, c: Coord) -> Result<(), PlacementError> {
+        let i = match self.ruins.iter().position(|ruin| ruin.contains(&c)) {
+            Some(i) => i,
+            None => return Ok(()),
+        };
+        let map = &self.map;
+        let rubble: Vec<Coord> = self.ruins[i].iter().cloned()
+            .filter(|c| map.get(c).map_or(false, |t| t.rubble)).collect();
+        let cost = self.road_cost.times(rubble.len() as i32);
+        self.resources.try_spend(&cost).map_err(PlacementError::Unaffordable)?;
+        self.ledger.record(self.tick, Category::Building, &cost.times(-1));
+        for c in rubble {
+            if let Some(tile) = self.map.get_mut(&c) {
+                tile.rubble = false;
             }
         }
-        
-        blit(&tcod.con, (0,0), (self.mapxy.x, self.mapxy.y), &mut tcod.root, (0,0), 1.0, 1.0); 
+        self.ruins.remove(i);
+        Ok(())
     }
-}
 
-#[derive(Clone, Copy, Debug)]
-pub struct Tile {
-    blocked: bool,
-    road:    bool,
-}
+    // changes the terrain of a tile nothing is built on
+    fn set_terrain(&mut self, c: Coord, terrain: Terrain) {
+        if let Some(tile) = self.map.get_mut(&c) {
+            if !tile.blocked {
+                tile.terrain = terrain;
+            }
+        }
+    }
 
-impl Tile {
-    pub fn empty() -> Self {
-        Tile {
-            blocked: false,
-            road:    false,
+    // adds an already paid for road tile
+    fn add_road(&mut self, coord: Coord) {
+        let water = self.map.get(&coord).map_or(false, |t| t.terrain == Terrain::Water);
+        self.map.set(coord.clone(), if water { Tile::bridge() } else { Tile::road() });
+    }
+
+    fn block(&mut self, e: Entity, newobj: &Building) {
+        for c in newobj.tiles() {
+            if let Some(tile) = self.map.get_mut(&c) {
+                tile.blocked = true;
+                tile.occupant = Some(e);
+            }
         }
     }
 
-    pub fn wall() -> Self {
-        Tile {
-            blocked: true,
-            road:    false,
+    // the object type drawn at the bottom of the screen at c, if any
+    fn palette_at(&self, c: Coord) -> Option<usize> {
+        let x = c.x / 4 - 1;
+        if c.y < self.mapxy.y || x < 0 || x as usize >= self.buildingtypes.len() {
+            return None
         }
+        Some(x as usize)
     }
 
-    pub fn road() -> Self {
-        Tile {
-            blocked: true,
-            road:    true,
+    fn get_building_to_build(&self, u: usize) -> Building {
+        let mut b = self.buildingtypes[u].clone().new_token(self.mousexy);
+        for _ in 0..self.rotation {
+            b.rotate();
         }
+        b
     }
-}
 
-fn von_neumann_neighbours(p: Coord) -> [Coord; 4] {
-    [ Coord::new(p.x + 1, p.y),
-      Coord::new(p.x - 1, p.y),
-      Coord::new(p.x, p.y + 1),
-      Coord::new(p.x, p.y - 1) ]
-}
+    // the buildings covering the area dragged out since the button went down,
+    // stepping by the size of the building and skipping blocked spots
+    fn get_buildings_to_build(&self, u: usize) -> Vec<Building> {
+        let template = self.get_building_to_build(u);
+        let Coord{x: dx, y: dy} = template.footprint.dimxy;
+        let start = self.drag.unwrap_or(self.mousexy);
+        let end = self.mousexy;
+        let stepx = if end.x < start.x { -dx } else { dx };
+        let stepy = if end.y < start.y { -dy } else { dy };
+        let mut buildings = vec![];
+        for j in 0..=(end.y - start.y).abs() / dy {
+            for i in 0..=(end.x - start.x).abs() / dx {
+                let b = template.clone().new_token(Coord::new(start.x + i*stepx, start.y + j*stepy));
+                if self.check_site(&b).is_ok() {
+                    buildings.push(b);
+                }
+            }
+        }
+        buildings
+    }
 
-struct Map<'a> {
-    coords: &'a mut HashMap<Coord,Tile>
-}
+    // the tiles of the road dragged out so far, in order
+    fn get_road_to_build(&self) -> Vec<Coord> {
+        let waypoints = match self.draw {
+            Draw::Road(ref w) if !w.is_empty() => w.clone(),
+            _ => vec![self.mousexy],
+        };
+        let mut road = vec![waypoints[0]];
+        for w in waypoints.windows(2) {
+            if let Some(mut path) = self.route(w[0], w[1]) {
+                // routes come back from goal to start
+                path.reverse();
+                for p in path.windows(2) {
+                    road.extend(straight_line(p[0], p[1]));
+                }
+            }
+        }
+        road
+    }
 
-impl<'a> path::Map for &Map<'a> {
-    type Node = Coord; 
-    fn neighbours(&self, n: Self::Node) -> Vec<Self::Node> {
-        let mut neighbours: Vec<Self::Node> = vec![];
-        for p in von_neumann_neighbours(n).iter() {
-            let t = self.coords.get(&p);
-            if let Some(tile) = t {
-                if !tile.blocked || tile.road {
-                    neighbours.push(*p);
+    // the new road tiles in a road, in order, skipping those that are road
+    // already and those the road passed before when it doubles back
+    fn new_road_tiles(&self, road: &[Coord]) -> Vec<Coord> {
+        let mut seen = HashSet::new();
+        road.iter().filter(|&&c| seen.insert(c) && self.check_road_site(c).is_ok()).cloned().collect()
+    }
+
+    // the route a road takes between two points, over bridges if we may build them
+    fn route(&self, start: Coord, goal: Coord) -> Option<Vec<Coord>> {
+        if self.bridging {
+            // all resources counted alike, a tile weighs what it costs
+            let weight = |cost: &Resources| cost.0.iter().map(|&v| v as i64).sum::<i64>().max(1);
+            let bridging = Bridging{ map: &self.map, road: weight(&self.road_cost), bridge: weight(&self.bridge_cost) };
+            path::find_route(bridging, start, goal)
+        } else {
+            path::find_route(&self.map, start, goal)
+        }
+    }
+
+    fn tile_cost(&self, c: Coord) -> Resources {
+        match self.map.get(&c) {
+            Some(tile) if tile.terrain == Terrain::Water => self.bridge_cost.clone(),
+            _ => self.road_cost.clone(),
+        }
+    }
+
+    fn tiles_cost(&self, tiles: &[Coord]) -> Resources {
+        tiles.iter().fold(Resources(vec![0; self.road_cost.len()]), |cost, &c| cost.plus(&self.tile_cost(c)))
+    }
+
+    fn road_cost_of(&self, road: &[Coord]) -> Resources {
+        self.tiles_cost(&self.new_road_tiles(road))
+    }
+
+    // the new tiles of the dragged road, cut off where money runs out
+    fn get_affordable_road(&self) -> Vec<Coord> {
+        let mut total = Resources(vec![0; self.road_cost.len()]);
+        self.new_road_tiles(&self.get_road_to_build()).into_iter().take_while(|&c| {
+            total = total.plus(&self.tile_cost(c));
+            self.resources.covers(&total)
+        }).collect()
+    }
+
+    // assigns a road network id to every road tile
+    fn road_networks(&self) -> Grid<Option<usize>> {
+        let mut networks = Grid::new(self.mapxy, None);
+        let mut next = 0;
+        for (c, tile) in self.map.tiles.iter() {
+            if !tile.road || networks.get(c) != Some(&None) {
+                continue
+            }
+            let id = next;
+            next += 1;
+            let mut stack = vec![c];
+            networks.set(c, Some(id));
+            while let Some(p) = stack.pop() {
+                for n in von_neumann_neighbours(p).iter() {
+                    if self.map.get(n).map_or(false, |t| t.road) && networks.get(*n) == Some(&None) {
+                        networks.set(*n, Some(id));
+                        stack.push(*n);
+                    }
                 }
             }
         }
-        neighbours
+        networks
     }
 
-    fn g(&self, _n: Self::Node, _neighbour: Self::Node) -> i64 { 1 }
+    // the road network a building can be reached by, from its entrance
+    fn network_of(&self, networks: &Grid<Option<usize>>, b: &Building) -> Option<usize> {
+        von_neumann_neighbours(b.entrance()).iter().filter_map(|n| networks.get(*n).cloned().flatten()).next()
+    }
 
-    fn h(&self, n: Self::Node, goal: Self::Node) -> i64 {
-        let dx = goal.x - n.x;
-        let dy = goal.y - n.y;
-        (dx.abs() + dy.abs()) as i64
+    // houses supply workers to buildings on the same road network,
+    // first come first served in the order buildings were placed
+    fn assign_workers(&mut self) {
+        let networks = self.road_networks();
+        let ids: HashMap<Entity, usize> = self.buildings.iter()
+            .filter_map(|(e, b)| self.network_of(&networks, b).map(|id| (e, id))).collect();
+        let mut pool: HashMap<usize, i32> = HashMap::new();
+        for (&e, &id) in ids.iter() {
+            if self.is_active(e) {
+                *pool.entry(id).or_insert(0) += self.residents(e);
+            }
+        }
+        for (e, state) in self.states.iter_mut() {
+            let workers = self.buildings.get(e).map_or(0, |b| b.workers);
+            state.staffed = 0;
+            if let Some(available) = ids.get(&e).and_then(|id| pool.get_mut(id)) {
+                state.staffed = std::cmp::min(workers, *available);
+                *available -= state.staffed;
+            }
+        }
     }
-}
 
-impl<'a> Map<'a> {
-    fn new(m: &'a mut HashMap<Coord,Tile>) -> Self {
-        Map{ coords: m }
+    fn is_active(&self, e: Entity) -> bool {
+        self.states.get(e).map_or(false, |s| s.working())
     }
 
-    fn get(&self, c: &Coord) -> Option<&Tile> {
-        self.coords.get(c)
+    // people living in a building at its current level
+    fn residents(&self, e: Entity) -> i32 {
+        match (self.buildings.get(e), self.states.get(e)) {
+            (Some(b), Some(state)) => b.residents_at(state.level),
+            _ => 0,
+        }
     }
 
-    fn set(&mut self, c: Coord, t: Tile) {
-        self.coords.insert(c, t);
+    pub fn population(&self) -> i32 {
+        self.buildings.iter().filter(|&(e, _)| self.is_active(e)).map(|(e, _)| self.residents(e)).sum()
     }
-}
 
-pub fn make_map(dimxy: Coord) -> HashMap<Coord, Tile> {
-    let mut m = HashMap::new();
-    for y in 0..dimxy.y {
-        for x in 0..dimxy.x {
-            if x > (2*dimxy.x/3) {
-                m.insert(Coord::new(x,y), Tile::wall());
-            } else {
-                m.insert(Coord::new(x,y), Tile::empty());
+
+    // runs a single simulation tick
+    fn update_buildings(&mut self) {
+        self.tick += 1;
+        let i = self.tick;
+        self.assign_workers();
+        let mut sending = vec![];
+        for e in self.buildings.entities() {
+            let (b, state) = match (self.buildings.get(e), self.states.get_mut(e)) {
+                (Some(b), Some(state)) => (b, state),
+                _ => continue,
+            };
+            state.tick();
+            state.fire += b.fire_risk;
+            state.damage += b.damage_risk;
+            if b.walker.is_some() && state.working() && state.due(walker::WALKER_PERIOD) {
+                sending.push(e);
+            }
+            if state.due(UPKEEP_PERIOD) && !b.upgrades.is_empty() {
+                // houses move up, or back down, with the land they stand on
+                state.level = b.level_for(self.map.desirability(b.entrance()));
+            }
+            if state.due(UPKEEP_PERIOD) && !b.upkeep.is_zero() {
+                state.active = match self.resources.try_spend(&b.upkeep) {
+                    Ok(()) => {
+                        self.ledger.record(i, Category::Upkeep, &b.upkeep.times(-1));
+                        true
+                    },
+                    Err(Insufficient(shortfalls)) => {
+                        for s in shortfalls {
+                            self.short[s.resource] = true;
+                        }
+                        false
+                    },
+                };
+            }
+            if !state.working() || !state.due(b.period) {
+                continue
+            }
+            let produced = match self.run_event(e, |behavior, ctx| behavior.update(ctx)) {
+                Some(produced) => produced,
+                None => continue,
+            };
+            let (produced, income) = match (self.buildings.get(e), self.states.get_mut(e)) {
+                (Some(b), Some(state)) => {
+                    self.research[b.btype] += state.staffed_research(b);
+                    let produced = state.staffed_output(b, &produced);
+                    (if state.boosted > 0 { produced.times(2) } else { produced }, b.income)
+                },
+                _ => continue,
+            };
+            self.collect(e, &produced, income);
+        }
+        self.ledger.sample(i, &self.resources);
+        for e in sending {
+            self.send_walker(e);
+        }
+        if i % walker::WALKER_STEP == 0 {
+            self.move_walkers();
+        }
+        self.check_risks();
+        if i % events::EVENT_PERIOD == 0 {
+            self.trigger_events();
+        }
+        if let (Some(ref mut prices), 0) = (self.prices.as_mut(), i % prices::PRICE_PERIOD) {
+            prices.update(&self.resources, &self.ledger);
+        }
+        self.receive_ships();
+        if i % storage::EMPTY_PERIOD == 0 {
+            self.empty_stores();
+        }
+        self.balance_stores();
+        self.check_unlocks();
+        self.check_scenario();
+    }
+
+    // whether ships have somewhere to come to
+    fn has_trade_post(&self) -> bool {
+        self.trade_post().is_some()
+    }
+
+    // the entrance of the first working trade post
+    fn trade_post(&self) -> Option<Coord> {
+        self.buildings.iter().find(|&(e, b)| b.trade_post && self.is_active(e)).map(|(_, b)| b.entrance())
+    }
+
+    fn is_storable(&self, r: usize) -> bool {
+        self.storable.get(r).cloned().unwrap_or(false)
+    }
+
+    // puts up to n of a good into working storage around c, the nearest
+    // first, and returns how much found room
+    fn store_near(&mut self, c: Coord, r: usize, n: i32, range: Option<i32>) -> i32 {
+        let mut near: Vec<(i32, Entity)> = self.stores.iter()
+            .filter(|&(s, store)| store.room(r) > 0 && self.is_active(s))
+            .filter_map(|(s, _)| self.buildings.get(s).map(|b| (manhattan(b.entrance(), c), s)))
+            .filter(|&(d, _)| range.map_or(true, |range| d <= range)).collect();
+        near.sort();
+        let mut left = n;
+        for (_, s) in near {
+            if let Some(store) = self.stores.get_mut(s) {
+                left -= store.put(r, left);
             }
         }
+        n - left
     }
-    m
-}
 
+    // stores set to empty send their goods on to stores that take them
+    fn empty_stores(&mut self) {
+        for s in self.stores.entities() {
+            let entrance = match self.buildings.get(s) {
+                Some(b) => b.entrance(),
+                None => continue,
+            };
+            for r in 0..self.resources.len() {
+                let room: i32 = self.stores.iter().filter(|&(o, _)| o != s && self.is_active(o)).map(|(_, o)| o.room(r)).sum();
+                let n = match self.stores.get_mut(s) {
+                    Some(store) if store.setting(r) == Setting::Empty => store.take(r, room.min(storage::EMPTY_AMOUNT)),
+                    _ => continue,
+                };
+                self.store_near(entrance, r, n, None);
+            }
+        }
+    }
+
+    // keeps storage in line with the stock: what was spent comes out of
+    // storage once nothing is left lying around, stores being emptied
+    // first; the stock then takes no more than storage has room for
+    fn balance_stores(&mut self) {
+        for r in 0..self.resources.len() {
+            if !self.is_storable(r) {
+                continue
+            }
+            let stored: i32 = self.stores.values().map(|s| s.amount(r)).sum();
+            let mut excess = stored - self.resources.get(r);
+            for setting in [Setting::Empty, Setting::Reject, Setting::Accept].iter() {
+                for (_, store) in self.stores.iter_mut().filter(|(_, s)| s.setting(r) == *setting) {
+                    if excess <= 0 {
+                        break
+                    }
+                    excess -= store.take(r, excess);
+                }
+            }
+            let room: i32 = self.stores.iter().filter(|&(s, _)| self.is_active(s)).map(|(_, store)| store.room(r)).sum();
+            self.resources.set_cap(r, self.resources.get(r) + room);
+        }
+    }
+
+    // every partner whose ship is due trades at the trade post
+    fn receive_ships(&mut self) {
+        let trade = match self.trade.take() {
+            Some(trade) => trade,
+            None => return,
+        };
+        let tick = self.tick;
+        if self.has_trade_post() {
+            for p in trade.partners().iter().filter(|p| tick % p.period == 0) {
+                let change = trade.visit(&self.partner_prices(p), &self.resources);
+                if change.is_zero() {
+                    continue
+                }
+                for (r, &v) in change.0.iter().enumerate() {
+                    // imports are carried to storage from the trade post
+                    if v > 0 && self.is_storable(r) {
+                        if let Some(post) = self.trade_post() {
+                            self.store_near(post, r, v, None);
+                        }
+                    }
+                    self.resources.update(r, v);
+                }
+                self.ledger.record(self.tick, Category::Trade, &change);
+                self.log(format!("a ship from {} traded {}", p.name, format_resources(&change)));
+            }
+        }
+        self.trade = Some(trade);
+    }
+
+    // what the partner asks and pays today
+    fn partner_prices(&self, partner: &Partner) -> Partner {
+        match self.prices {
+            Some(ref prices) => prices.adjust(partner),
+            None => partner.clone(),
+        }
+    }
+
+    fn road_next_to(&self, c: Coord) -> Option<Coord> {
+        von_neumann_neighbours(c).iter().cloned().find(|n| self.map.get(n).map_or(false, |t| t.road))
+    }
+
+    fn risk(&self, e: Entity, kind: WalkerKind) -> i32 {
+        match (self.states.get(e), kind) {
+            (Some(s), WalkerKind::Prefect) => s.fire,
+            (Some(s), WalkerKind::Engineer) => s.damage,
+            _ => 0,
+        }
+    }
+
+    // sends a walker from e to the building in range most at risk,
+    // unless one from e is still out
+    fn send_walker(&mut self, e: Entity) {
+        if self.walkers.iter().any(|w| w.home == e) {
+            return
+        }
+        let (kind, entrance) = match self.buildings.get(e) {
+            Some(b) => match b.walker {
+                Some(kind) => (kind, b.entrance()),
+                None => return,
+            },
+            None => return,
+        };
+        let start = match self.road_next_to(entrance) {
+            Some(start) => start,
+            None => return,
+        };
+        let target = self.buildings_within(entrance, walker::WALKER_RANGE, None).into_iter()
+            .filter(|&o| o != e && self.risk(o, kind) > 0)
+            .filter_map(|o| self.buildings.get(o).and_then(|b| self.road_next_to(b.entrance())).map(|c| (o, c)))
+            .max_by_key(|&(o, _)| self.risk(o, kind));
+        if let Some((_, goal)) = target {
+            if let Some(w) = Walker::new(kind, e, &self.map, start, goal) {
+                self.walkers.push(w);
+            }
+        }
+    }
+
+    // walkers take a step and look after every building within reach
+    fn move_walkers(&mut self) {
+        let mut walkers = std::mem::replace(&mut self.walkers, vec![]);
+        for w in walkers.iter_mut() {
+            w.step();
+            let r = walker::WALKER_REACH;
+            for y in w.pos.y-r..=w.pos.y+r {
+                for x in w.pos.x-r..=w.pos.x+r {
+                    let occupant = self.map.get(&Coord::new(x, y)).and_then(|t| t.occupant);
+                    if let Some(state) = occupant.and_then(|o| self.states.get_mut(o)) {
+                        match w.kind {
+                            WalkerKind::Prefect => state.fire = 0,
+                            WalkerKind::Engineer => state.damage = 0,
+                        }
+                    }
+                }
+            }
+        }
+        let buildings = &self.buildings;
+        walkers.retain(|w| !w.arrived() && buildings.get(w.home).is_some());
+        self.walkers = walkers;
+    }
+
+    // buildings nobody looked after for too long burn down or collapse
+    fn check_risks(&mut self) {
+        let ruined: Vec<(Entity, bool)> = self.states.iter()
+            .filter(|(_, s)| s.fire >= walker::RISK_THRESHOLD || s.damage >= walker::RISK_THRESHOLD)
+            .map(|(e, s)| (e, s.fire >= walker::RISK_THRESHOLD)).collect();
+        for (e, burned) in ruined {
+            let name = match self.buildings.get(e) {
+                Some(b) => self.name_of(b.btype),
+                None => continue,
+            };
+            self.ruin(e);
+            let text = if burned { format!("a {} burned down", name) } else { format!("a {} collapsed", name) };
+            self.log(text);
+        }
+    }
+
+    pub fn log(&mut self, text: String) {
+        let tick = self.tick;
+        if let Some(ref mut events) = self.events {
+            events.record(tick, text);
+        }
+    }
+
+    // every kind of event picks a building it could hit and rolls against it
+    fn trigger_events(&mut self) {
+        let mut events = match self.events.take() {
+            Some(events) => events,
+            None => return,
+        };
+        for (kind, chance) in events.chances() {
+            let candidates: Vec<Entity> = self.buildings.iter()
+                .filter(|&(_, b)| self.can_hit(kind, b)).map(|(e, _)| e).collect();
+            if candidates.is_empty() {
+                continue
+            }
+            let e = candidates[events.pick(candidates.len())];
+            let chance = if self.is_protected(e, kind) { chance / events::PREVENTION } else { chance };
+            if events.roll(chance) {
+                let text = self.apply_event(e, kind);
+                events.record(self.tick, text);
+            }
+        }
+        self.events = Some(events);
+    }
+
+    fn can_hit(&self, kind: EventKind, b: &Building) -> bool {
+        match kind {
+            EventKind::Fire | EventKind::Storm => true,
+            EventKind::Flood => self.map.near_terrain(b, Terrain::Water, 2),
+            EventKind::Plague => b.residents > 0,
+            EventKind::Windfall => b.workers > 0,
+        }
+    }
+
+    // whether a working building that prevents the event is close enough
+    fn is_protected(&self, e: Entity, kind: EventKind) -> bool {
+        let target = match self.buildings.get(e) {
+            Some(b) => b,
+            None => return false,
+        };
+        // no building types prevent it from further away than this
+        let max_radius = self.buildingtypes.iter().flat_map(|b| b.prevents.iter())
+            .filter(|&&(k, _)| k == kind).map(|&(_, radius)| radius).max();
+        let max_radius = match max_radius {
+            Some(radius) => radius,
+            None => return false,
+        };
+        self.spatial.within(target.entrance(), max_radius, None).into_iter().any(|o| {
+            self.is_active(o) && self.buildings.get(o).map_or(false, |b| {
+                b.prevents.iter().any(|&(k, radius)| k == kind && b.distance(target) <= radius)
+            })
+        })
+    }
+
+    // returns a line for the event log
+    fn apply_event(&mut self, e: Entity, kind: EventKind) -> String {
+        let name = match self.buildings.get(e) {
+            Some(b) => self.name_of(b.btype),
+            None => return String::new(),
+        };
+        let state = match self.states.get_mut(e) {
+            Some(state) => state,
+            None => return String::new(),
+        };
+        match kind {
+            EventKind::Fire => {
+                self.ruin(e);
+                format!("{} burned down a {}", kind.name(), name)
+            },
+            EventKind::Windfall => {
+                state.boosted = kind.duration();
+                format!("{}: a {} produces double", kind.name(), name)
+            },
+            _ => {
+                state.disabled = std::cmp::max(state.disabled, kind.duration());
+                format!("{} struck a {}", kind.name(), name)
+            },
+        }
+    }
+
+    // how many buildings of each type are standing
+    fn built(&self) -> Vec<i32> {
+        let mut built = vec![0; self.buildingtypes.len()];
+        for b in self.buildings.values() {
+            if let Some(n) = built.get_mut(b.btype) {
+                *n += 1;
+            }
+        }
+        built
+    }
+
+    fn is_met(&self, requirement: &Requirement, built: &[i32]) -> bool {
+        match *requirement {
+            Requirement::Population(n) => self.population() >= n,
+            Requirement::Built(b) => built.get(b).map_or(false, |&n| n > 0),
+            Requirement::Research{ at, points } => self.research.get(at).map_or(false, |&r| r >= points),
+        }
+    }
+
+    // building types stay unlocked once all their requirements were met
+    fn check_unlocks(&mut self) {
+        let built = self.built();
+        for (b, btype) in self.buildingtypes.iter().enumerate() {
+            if !self.unlocked[b] && btype.requires.iter().all(|r| self.is_met(r, &built)) {
+                self.unlocked[b] = true;
+            }
+        }
+    }
+
+    fn check_scenario(&mut self) {
+        let progress = Progress{
+            tick:       self.tick,
+            population: self.population(),
+            stock:      &self.resources,
+            short:      &self.short,
+            built:      self.built(),
+        };
+        if let Some(ref mut scenario) = self.scenario {
+            scenario.check(&progress);
+        }
+        self.short = vec![false; self.short.len()];
+    }
+
+    // runs one behavior event for e, applies the terrain edits it
+    // asked for and returns what it produced
+    fn run_event<F>(&mut self, e: Entity, event: F) -> Option<Resources>
+    where F: FnOnce(&mut dyn BuildingBehavior, &mut Context)
+    {
+        let b = self.buildings.get(e)?;
+        let buildings = &self.buildings;
+        let neighbours = self.buildings_within(b.entrance(), NEIGHBOUR_RADIUS, None).into_iter()
+            .filter(|&o| o != e).filter_map(|o| buildings.get(o)).collect();
+        let behavior = self.behaviors.get_mut(e)?;
+        let mut ctx = Context{
+            entity:     e,
+            building:   b,
+            map:        &self.map,
+            neighbours: neighbours,
+            spatial:    &self.spatial,
+            stock:      &self.resources,
+            ledger:     &self.ledger,
+            tick:       self.tick,
+            produced:   Resources(vec![0; self.resources.len()]),
+            edits:      vec![],
+            errors:     vec![],
+        };
+        event(&mut **behavior, &mut ctx);
+        let Context{ produced, edits, errors, building, .. } = ctx;
+        let name = self.name_of(building.btype);
+        for (c, terrain) in edits {
+            self.set_terrain(c, terrain);
+        }
+        for error in errors {
+            self.log(format!("a {}: {}", name, error));
+        }
+        Some(produced)
+    }
+
+    // buildings report what they produce, the stock decides what fits;
+    // only what was stored is booked, under the given category
+    fn collect(&mut self, e: Entity, produced: &Resources, category: Category) {
+        let entrance = match self.buildings.get(e) {
+            Some(b) => b.entrance(),
+            None => return,
+        };
+        // goods that need storage are only produced as far as it has room
+        let mut produced = produced.clone();
+        let mut stalled = false;
+        for r in 0..produced.len() {
+            let v = produced.get(r);
+            if v > 0 && self.is_storable(r) {
+                let kept = self.store_near(entrance, r, v, Some(storage::STORAGE_RANGE));
+                stalled |= kept < v;
+                produced.set(r, kept);
+            }
+        }
+        if let Some(state) = self.states.get_mut(e) {
+            state.stalled = stalled;
+        }
+        let produced = &produced;
+        let rest = self.resources.deposit(produced);
+        // what a building used up but we did not have
+        for (r, &v) in rest.0.iter().enumerate() {
+            self.short[r] |= v < 0;
+        }
+        let stored = Resources(produced.0.iter().zip(rest.0.iter()).map(|(p, r)| p-r).collect());
+        self.ledger.record(self.tick, category, &stored);
+    }
+
+    // bridges run across the water between the land on either end
+    fn bridge_glyph(&self, c: Coord) -> u8 {
+        let across = |d: i32| self.map.get(&Coord::new(c.x + d, c.y)).map_or(false, |t| t.terrain == Terrain::Ground || t.road);
+        if across(-1) || across(1) { 205 } else { 186 }
+    }
+
+    // the overlay after the one shown: the terrain, then every layer of
+    // the map in the order they were added, then back to the terrain
+    fn next_overlay(&self) -> Overlay {
+        let mut overlays = vec![Overlay::Terrain];
+        overlays.extend(self.map.layers().iter().map(|l| Overlay::Layer(l.name, l.scale)));
+        let i = overlays.iter().position(|o| o.name() == self.overlay.name()).unwrap_or(0);
+        overlays[(i + 1) % overlays.len()]
+    }
+
+    // works out again the layers that follow from the buildings
+    fn update_layers(&mut self) {
+        for &name in [COVERAGE, ROAD_NETWORKS, FIRE_RISK, POPULATION].iter() {
+            let values = self.compute_layer(name);
+            if let Some(layer) = self.map.layer_mut(name) {
+                *layer = values;
+            }
+        }
+    }
+
+    fn compute_layer(&self, name: &str) -> Grid<i32> {
+        let mut layer = Grid::new(self.mapxy, 0);
+        match name {
+            COVERAGE => {
+                // services reach as far as the events they prevent,
+                // or as far as their walkers go
+                for (_, b) in self.buildings.iter().filter(|&(e, _)| self.is_active(e)) {
+                    let mut radius = b.prevents.iter().map(|&(_, r)| r).max();
+                    if b.walker.is_some() {
+                        radius = radius.max(Some(walker::WALKER_RANGE));
+                    }
+                    let radius = match radius {
+                        Some(radius) => radius,
+                        None => continue,
+                    };
+                    let entrance = b.entrance();
+                    for y in entrance.y-radius..=entrance.y+radius {
+                        for x in entrance.x-radius..=entrance.x+radius {
+                            let c = Coord::new(x, y);
+                            if (x - entrance.x).abs() + (y - entrance.y).abs() <= radius {
+                                if let Some(v) = layer.get_mut(c) {
+                                    *v += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            ROAD_NETWORKS => {
+                // buildings take the colour of the network at their entrance
+                let networks = self.road_networks();
+                for (c, id) in networks.iter() {
+                    if let Some(id) = *id {
+                        layer.set(c, id as i32 + 1);
+                    }
+                }
+                for b in self.buildings.values() {
+                    if let Some(id) = self.network_of(&networks, b) {
+                        for c in b.tiles() {
+                            layer.set(c, id as i32 + 1);
+                        }
+                    }
+                }
+            },
+            FIRE_RISK => {
+                for (e, b) in self.buildings.iter() {
+                    let fire = self.states.get(e).map_or(0, |s| s.fire);
+                    for c in b.tiles() {
+                        layer.set(c, fire * 100 / walker::RISK_THRESHOLD);
+                    }
+                }
+            },
+            POPULATION => {
+                for (e, b) in self.buildings.iter().filter(|&(e, _)| self.is_active(e)) {
+                    for c in b.tiles() {
+                        layer.set(c, self.residents(e));
+                    }
+                }
+            },
+            _ => {},
+        }
+        layer
+    }
+
+    fn render_all(&self, tcod: &mut Tcod) {
+        if self.outcome() != Outcome::Playing {
+            self.render_outcome(tcod);
+            blit(&tcod.con, (0,0), (self.mapxy.x, self.mapxy.y), &mut tcod.root, (0,0), 1.0, 1.0); 
+            return
+        }
+        if self.show_trade {
+            self.render_trade(tcod);
+            blit(&tcod.con, (0,0), (self.mapxy.x, self.mapxy.y), &mut tcod.root, (0,0), 1.0, 1.0); 
+            return
+        }
+        if self.show_stats {
+            self.render_stats(tcod);
+            blit(&tcod.con, (0,0), (self.mapxy.x, self.mapxy.y), &mut tcod.root, (0,0), 1.0, 1.0); 
+            return
+        }
+        // go through all tiles, and set their background color,
+        // tinted by the overlay if there is one
+        let layer = match self.overlay {
+            Overlay::Terrain => None,
+            Overlay::Layer(name, _) => self.map.layer(name),
+        };
+        let scale = self.overlay.scale();
+        for (y, row) in self.map.tiles.rows().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let (x, y) = (x as i32, y as i32);
+                let mut background = if tile.blocked {
+                    COLOR_DARK_WALL
+                } else if tile.terrain == Terrain::Water {
+                    COLOR_WATER
+                } else {
+                    COLOR_DARK_GROUND
+                };
+                if let Some(&v) = layer.and_then(|l| l.get(Coord::new(x, y))) {
+                    background = scale.color(background, v);
+                }
+                tcod.con.set_char_background(x, y, background, BackgroundFlag::Set);
+                if tile.is_bridge() {
+                    tcod.con.set_default_foreground(LIGHT_SEPIA);
+                    tcod.con.put_char(x, y, self.bridge_glyph(Coord::new(x, y)) as char, BackgroundFlag::None);
+                } else if tile.road {
+                    tcod.con.set_default_foreground(GREY);
+                    tcod.con.put_char(x, y, 178 as char, BackgroundFlag::None);
+                }
+                if tile.rubble {
+                    tcod.con.set_default_foreground(DARK_GREY);
+                    tcod.con.put_char(x, y, 176 as char, BackgroundFlag::None);
+                }
+            }
+        }
+        // draw all objects in the list
+        for (e, object) in self.buildings.iter() {
+            if self.selected == Some(e) {
+                object.draw_in(&mut tcod.con, WHITE);
+            } else if !self.is_active(e) {
+                // buildings that stopped working are greyed out
+                object.draw_in(&mut tcod.con, DARK_GREY);
+            } else if let Some(level) = self.states.get(e).map(|s| s.level).filter(|&l| l > 0) {
+                // upgraded houses are drawn lighter with every level
+                object.draw_in(&mut tcod.con, lerp(object.color(), WHITE, 0.25 * level as f32));
+            } else {
+                object.draw(&mut tcod.con);
+            }
+        }
+        for w in self.walkers.iter() {
+            let color = match w.kind {
+                WalkerKind::Prefect => LIGHT_RED,
+                WalkerKind::Engineer => LIGHT_YELLOW,
+            };
+            tcod.con.set_default_foreground(color);
+            tcod.con.put_char(w.pos.x, w.pos.y, 2 as char, BackgroundFlag::None);
+        }
+    
+        // draw object types at the bottom of the screen
+        for (i, object) in self.buildingtypes.iter().enumerate() {
+            let mut o = object.clone();
+            o.topleft = Coord::new((i+1) as i32*4, self.mapxy.y + 1);
+            if self.is_available(i) {
+                o.draw(&mut tcod.root);
+            } else {
+                o.draw_in(&mut tcod.root, DARK_GREY);
+            }
+        }
+
+        // tooltip for the object type under the mouse
+        if let Some(hover) = self.palette_at(self.mousexy) {
+            let b = &self.buildingtypes[hover];
+            let mut tip = format!("cost {}", format_resources(&b.cost));
+            if !self.available[hover] {
+                tip = format!("locked  {}", tip);
+            } else if !self.unlocked[hover] {
+                let built = self.built();
+                let missing: Vec<String> = b.requires.iter().filter(|r| !self.is_met(r, &built))
+                    .map(|r| r.describe(&self.names, &self.research)).collect();
+                tip = format!("requires {}  {}", missing.join(", "), tip);
+            }
+            if !b.upkeep.is_zero() {
+                tip = format!("{}  upkeep {} per {} ticks", tip, format_resources(&b.upkeep), UPKEEP_PERIOD);
+            }
+            if b.residents > 0 {
+                tip = format!("{}  houses {}", tip, b.residents);
+            }
+            if let Some(&(d, r)) = b.upgrades.last() {
+                tip = format!("{}  ({} at desirability {})", tip, r, d);
+            }
+            if b.workers > 0 {
+                tip = format!("{}  needs {} workers", tip, b.workers);
+            }
+            if !b.storage.is_zero() {
+                tip = format!("{}  stores {}", tip, format_resources(&b.storage));
+            }
+            tcod.con.set_default_foreground(WHITE);
+            tcod.con.print_rect(1, self.mapxy.y - 1, 100, 0, tip);
+        }
+    
+        // currently selected buildingtype to draw
+        let mut costs = Resources(Vec::new());
+        if let Draw::Building(u) = self.draw {
+            let mut current = self.get_building_to_build(u);
+            // draw everything being dragged out on the map as a preview
+            let previews = self.get_buildings_to_build(u);
+            for p in previews.iter() {
+                p.draw(&mut tcod.con);
+                p.draw_entrance(&mut tcod.con);
+            }
+            if previews.is_empty() {
+                current.draw(&mut tcod.con);
+            }
+            // tell why the building under the mouse cannot be placed
+            if self.mousexy.y < self.mapxy.y {
+                if let Err(e) = self.check_placement(&current) {
+                    tcod.con.set_default_foreground(RED);
+                    tcod.con.print_rect(1, 2, 100, 0, format!("{}", e));
+                }
+            }
+            costs = current.cost.times(std::cmp::max(previews.len(), 1) as i32);
+            // draw current at bottom-right as a reminder of current selection
+            current.topleft = Coord::new(self.mapxy.x-5, self.mapxy.y+1);
+            current.draw(&mut tcod.root);
+        }
+        if let Draw::Road(_) = self.draw {
+            let road = self.get_road_to_build();
+            costs = self.road_cost_of(&road);
+            // the part of the road we cannot afford is shown in red
+            let affordable = self.get_affordable_road();
+            for c in road.iter() {
+                let color = match self.map.get(c) {
+                    None => continue,
+                    Some(tile) if tile.road || affordable.contains(c) => WHITE,
+                    _ => RED,
+                };
+                let water = self.map.get(c).map_or(false, |t| t.terrain == Terrain::Water);
+                let glyph = if water { self.bridge_glyph(*c) } else { 178 };
+                tcod.con.set_default_foreground(color);
+                tcod.con.put_char(c.x, c.y, glyph as char, BackgroundFlag::None);
+            }
+            if self.bridging {
+                tcod.con.set_default_foreground(WHITE);
+                tcod.con.print_rect(1, 2, 100, 0, format!("bridges span up to {} water tiles, {} per tile",
+                    BRIDGE_SPAN, format_resources(&self.bridge_cost)));
+            }
+        }
+    
+        // draw messages
+        tcod.con.set_default_foreground(WHITE); 
+        for (i, r) in (0..4).enumerate() {
+            if r >= self.resources.len() {
+                continue
+            }
+            let c = resource_glyph(r);
+            // costs we cannot cover are shown in red
+            let color = if self.draw != Draw::None && costs.get(r as usize) > self.resources.get(r as usize) { RED } else { WHITE };
+            tcod.con.set_default_foreground(color);
+            let mut amount = format!("{}", self.resources.get(r as usize));
+            if self.resources.cap(r as usize) != std::i32::MAX {
+                amount = format!("{}/{}", amount, self.resources.cap(r as usize));
+            }
+            if self.draw == Draw::None || costs.get(r as usize) == 0 {
+                tcod.con.print_rect(i as i32 * 12 + 1, 1, 100, 0, format!("{}{}", c, amount));
+            } else {
+                tcod.con.print_rect(i as i32 * 12 + 1, 1, 100, 0, format!("{}{}({})", c, amount, costs.get(r as usize)));
+            }
+        }
+        let jobs: i32 = self.buildings.values().map(|b| b.workers).sum();
+        let employed: i32 = self.states.values().map(|s| s.staffed).sum();
+        tcod.con.set_default_foreground(WHITE);
+        tcod.con.print_rect(4 * 12 + 1, 1, 100, 0, format!("pop {} jobs {}/{}", self.population(), employed, jobs));
+
+        // info on the selected building
+        if let Some(e) = self.selected {
+            if let (Some(b), Some(state)) = (self.building(e), self.states.get(e)) {
+                let status = if !state.active {
+                    "inactive"
+                } else if state.disabled > 0 {
+                    "disabled"
+                } else if state.boosted > 0 {
+                    "boosted"
+                } else if state.stalled {
+                    "stalled, no storage room"
+                } else {
+                    "active"
+                };
+                let name = if b.upgrades.is_empty() {
+                    self.name_of(b.btype)
+                } else {
+                    format!("{} level {}", self.name_of(b.btype), state.level)
+                };
+                tcod.con.print_rect(1, self.mapxy.y - 1, 100, 0, format!("#{} {}  {}  workers {}/{}  fire {}% damage {}%  (del to demolish)",
+                    e.id(), name, status, state.staffed, b.workers,
+                    state.fire * 100 / walker::RISK_THRESHOLD, state.damage * 100 / walker::RISK_THRESHOLD));
+            }
+        }
+        let speed = if self.clock.paused() { "paused".to_string() } else { format!("x{}", self.clock.speed()) };
+        tcod.con.print_rect(self.mapxy.x - 8, 1, 100, 0, speed);
+
+        if let (Some(layer), true) = (layer, self.mousexy.y < self.mapxy.y) {
+            let v = layer.get(self.mousexy).cloned().unwrap_or(0);
+            tcod.con.set_default_foreground(WHITE);
+            tcod.con.print_rect(1, self.mapxy.y - 2, 100, 0, format!("{} {}  (o for next overlay)", self.overlay.name(), v));
+        }
+        if let Some(store) = self.selected.and_then(|e| self.stores.get(e)) {
+            self.render_store(tcod, store);
+        }
+        if self.show_goals {
+            self.render_goals(tcod, 3);
+        }
+        if self.show_log {
+            self.render_log(tcod, 3);
+        }
+        
+        blit(&tcod.con, (0,0), (self.mapxy.x, self.mapxy.y), &mut tcod.root, (0,0), 1.0, 1.0); 
+    }
+
+    // the goods in a storage building, above the selected building's info
+    fn render_store(&self, tcod: &mut Tcod, store: &Store) {
+        let goods: Vec<usize> = (0..self.resources.len()).filter(|&r| store.capacity(r) > 0).collect();
+        let top = self.mapxy.y - 3 - goods.len() as i32;
+        tcod.con.set_default_foreground(GREY);
+        tcod.con.print_rect(1, top, 100, 0, "[/] pick a good, a to accept, reject or empty");
+        for (i, &r) in goods.iter().enumerate() {
+            tcod.con.set_default_foreground(if r == store.selected { WHITE } else { GREY });
+            tcod.con.print_rect(1, top + 1 + i as i32, 100, 0, format!("{} {}{}/{} {}",
+                if r == store.selected { '>' } else { ' ' }, resource_glyph(r), store.amount(r), store.capacity(r), store.setting(r).name()));
+        }
+    }
+
+    // the scenario objectives, one per line from top down
+    fn render_goals(&self, tcod: &mut Tcod, top: i32) {
+        let scenario = match self.scenario {
+            Some(ref scenario) => scenario,
+            None => return,
+        };
+        tcod.con.set_default_foreground(WHITE);
+        tcod.con.print_rect(1, top, 100, 0, format!("{}  (g to hide)", scenario.title));
+        for (i, (goal, status)) in scenario.goals().into_iter().enumerate() {
+            let (mark, color) = match status {
+                Status::Pending => ('-', WHITE),
+                Status::Met     => ('+', GREEN),
+                Status::Failed  => ('x', RED),
+            };
+            tcod.con.set_default_foreground(color);
+            tcod.con.print_rect(1, top + 1 + i as i32, 100, 0, format!("{} {}", mark, goal));
+        }
+    }
+
+    // the latest events, right aligned at the top of the map
+    fn render_log(&self, tcod: &mut Tcod, top: i32) {
+        let events = match self.events {
+            Some(ref events) => events,
+            None => return,
+        };
+        tcod.con.set_default_foreground(LIGHT_RED);
+        for (i, line) in events.log().enumerate() {
+            tcod.con.print_ex(self.mapxy.x - 1, top + i as i32, BackgroundFlag::None, TextAlignment::Right, line);
+        }
+    }
+
+    // shown instead of the map once the scenario is won or lost
+    fn render_outcome(&self, tcod: &mut Tcod) {
+        let (title, color) = match self.outcome() {
+            Outcome::Victory => ("Victory!", GREEN),
+            Outcome::Defeat  => ("Defeat", RED),
+            Outcome::Playing => return,
+        };
+        let top = self.mapxy.y / 3;
+        tcod.con.set_default_foreground(color);
+        tcod.con.print_ex(self.mapxy.x / 2, top, BackgroundFlag::None, TextAlignment::Center, title);
+        tcod.con.set_default_foreground(GREY);
+        tcod.con.print_ex(self.mapxy.x / 2, top + 1, BackgroundFlag::None, TextAlignment::Center,
+            format!("after {} ticks with a population of {}", self.tick, self.population()));
+        self.render_goals(tcod, top + 3);
+        tcod.con.set_default_foreground(GREY);
+        tcod.con.print_ex(self.mapxy.x / 2, self.mapxy.y - 2, BackgroundFlag::None, TextAlignment::Center, "press enter to continue or escape to leave");
+    }
+
+    // the trade screen: what we keep of every good, and who comes to trade
+    fn render_trade(&self, tcod: &mut Tcod) {
+        let trade = match self.trade {
+            Some(ref trade) => trade,
+            None => return,
+        };
+        tcod.con.set_default_foreground(WHITE);
+        tcod.con.print_rect(1, 1, 100, 0, "Trade  (t to close)");
+        tcod.con.set_default_foreground(GREY);
+        tcod.con.print_rect(1, 2, 100, 0, "up/down pick a good, left/right import up to, -/+ export above, k keep all");
+        tcod.con.print_rect(1, 4, 100, 0, format!("{:<4}{:>14}{:>14}{:>14}{:>8}", "good", "stock", "import up to", "export above", "price"));
+        let mut y = 5;
+        for r in (0..self.resources.len()).filter(|&r| r != trade.currency()) {
+            let q = trade.quota(r);
+            let import = if q.import > 0 { format!("{}", q.import) } else { "-".to_string() };
+            let export = q.export.map_or("-".to_string(), |e| format!("{}", e));
+            let price = self.prices.as_ref().map_or("-".to_string(), |p| format!("{}", p.get(r)));
+            tcod.con.set_default_foreground(if r == trade.selected { WHITE } else { GREY });
+            tcod.con.print_rect(1, y, 100, 0, format!("{} {} {:>14}{:>14}{:>14}{:>8}",
+                if r == trade.selected { '>' } else { ' ' }, resource_glyph(r), self.resources.get(r), import, export, price));
+            y += 1;
+        }
+
+        y += 1;
+        tcod.con.set_default_foreground(WHITE);
+        tcod.con.print_rect(1, y, 100, 0, "Partners");
+        if !self.has_trade_post() {
+            tcod.con.set_default_foreground(RED);
+            tcod.con.print_rect(12, y, 100, 0, "(no working trade post, no ships will come)");
+        }
+        for p in trade.partners().iter().map(|p| self.partner_prices(p)) {
+            y += 1;
+            tcod.con.set_default_foreground(WHITE);
+            tcod.con.print_rect(1, y, 100, 0, format!("{}  a ship every {} ticks, next in {}",
+                p.name, p.period, p.period - self.tick % p.period));
+            tcod.con.set_default_foreground(GREY);
+            y += 1;
+            tcod.con.print_rect(3, y, 100, 0, format!("sells {}  buys {}", format_prices(&p.sells), format_prices(&p.buys)));
+        }
+    }
+
+    // the statistics screen: per resource its income and expenses
+    // over the last ticks and a graph of its stock over time
+    fn render_stats(&self, tcod: &mut Tcod) {
+        let n = self.resources.len() as i32;
+        let panel = std::cmp::min(self.mapxy.y / n, 11);
+        for r in 0..self.resources.len() {
+            let top = r as i32 * panel;
+            tcod.con.set_default_foreground(WHITE);
+            let mut title = format!("{}{}  net {:+} over {} ticks", resource_glyph(r), self.resources.get(r), self.ledger.net(r), ledger::WINDOW);
+            if let Some(ref prices) = self.prices {
+                title = format!("{}  price {} (base {})  worth {}", title, prices.get(r), prices.base(r), self.resources.get(r) * prices.get(r));
+            }
+            tcod.con.print_rect(1, top, 100, 0, title);
+            let mut line = String::new();
+            for c in Category::ALL.iter() {
+                let total = self.ledger.total(r, *c);
+                if total != 0 {
+                    line = format!("{}{} {:+}  ", line, c.name(), total);
+                }
+            }
+            tcod.con.set_default_foreground(GREY);
+            tcod.con.print_rect(1, top+1, 100, 0, line);
+
+            // bar graph of sampled stock levels, scaled to the highest sample
+            let height = panel - 3;
+            let history = self.ledger.history(r);
+            let max = std::cmp::max(history.iter().cloned().max().unwrap_or(0), 1);
+            tcod.con.set_default_foreground(LIGHT_BLUE);
+            for (x, &v) in history.iter().enumerate() {
+                let bar = v * height / max;
+                for y in 0..bar {
+                    tcod.con.put_char(x as i32 + 1, top + 1 + height - y, 219 as char, BackgroundFlag::None);
+                }
+            }
+
+            // prices drawn over the stock, scaled to the highest price
+            if let Some(ref prices) = self.prices {
+                let history = prices.history(r);
+                let max = std::cmp::max(history.iter().cloned().max().unwrap_or(0), 1);
+                tcod.con.set_default_foreground(LIGHT_YELLOW);
+                for (x, &v) in history.iter().enumerate() {
+                    tcod.con.put_char(x as i32 + 1, top + 1 + height - v * (height - 1) / max, 254 as char, BackgroundFlag::None);
+                }
+            }
+        }
+        if let Some(ref prices) = self.prices {
+            tcod.con.set_default_foreground(WHITE);
+            tcod.con.print_ex(self.mapxy.x - 1, 0, BackgroundFlag::None, TextAlignment::Right,
+                format!("stock worth {}", prices.value(&self.resources)));
+        }
+    }
+}
+
+fn format_resources(resources: &Resources) -> String {
+    let mut s = String::new();
+    for (r, &v) in resources.0.iter().enumerate() {
+        if v != 0 {
+            s = format!("{}{}{} ", s, resource_glyph(r), v);
+        }
+    }
+    s.trim_end().to_string()
+}
+
+// prices per unit of every good that has one
+fn format_prices(prices: &Resources) -> String {
+    if prices.is_zero() {
+        return "nothing".to_string()
+    }
+    format_resources(prices)
+}
+
+// TODO this should be more informed by the particular game
+fn resource_glyph(r: usize) -> char {
+    match r {
+        0 => 15 as char,  // Money
+        1 => 240 as char, // Wood
+        2 => 209 as char, // Tools
+        3 => 219 as char, // Stone
+        _ => '?',
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terrain {
+    Ground,
+    Water,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    blocked:  bool,
+    road:     bool,
+    // left by a building that burned down or collapsed, until cleared
+    rubble:   bool,
+    terrain:  Terrain,
+    // the building standing on this tile
+    occupant: Option<Entity>,
+}
+
+impl Tile {
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
+    pub fn is_road(&self) -> bool {
+        self.road
+    }
+
+    // a road over water
+    pub fn is_bridge(&self) -> bool {
+        self.road && self.terrain == Terrain::Water
+    }
+
+    pub fn is_rubble(&self) -> bool {
+        self.rubble
+    }
+
+    pub fn terrain(&self) -> Terrain {
+        self.terrain
+    }
+
+    pub fn occupant(&self) -> Option<Entity> {
+        self.occupant
+    }
+
+    pub fn empty() -> Self {
+        Tile {
+            blocked: false,
+            road:    false,
+            rubble:  false,
+            terrain: Terrain::Ground,
+            occupant: None,
+        }
+    }
+
+    pub fn road() -> Self {
+        Tile {
+            blocked: true,
+            road:    true,
+            rubble:  false,
+            terrain: Terrain::Ground,
+            occupant: None,
+        }
+    }
+
+    pub fn bridge() -> Self {
+        Tile {
+            blocked: true,
+            road:    true,
+            rubble:  false,
+            terrain: Terrain::Water,
+            occupant: None,
+        }
+    }
+
+    pub fn water() -> Self {
+        Tile {
+            blocked: false,
+            road:    false,
+            rubble:  false,
+            terrain: Terrain::Water,
+            occupant: None,
+        }
+    }
+}
+
+fn manhattan(a: Coord, b: Coord) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+// the tiles after a up to and including b, going straight from a to b
+fn straight_line(a: Coord, b: Coord) -> Vec<Coord> {
+    let (dx, dy) = ((b.x - a.x).signum(), (b.y - a.y).signum());
+    (1..=manhattan(a, b)).map(|i| Coord::new(a.x + i*dx, a.y + i*dy)).collect()
+}
+
+fn von_neumann_neighbours(p: Coord) -> [Coord; 4] {
+    [ Coord::new(p.x + 1, p.y),
+      Coord::new(p.x - 1, p.y),
+      Coord::new(p.x, p.y + 1),
+      Coord::new(p.x, p.y - 1) ]
+}
+
+// the layer holding how much people want to live on each tile,
+// from the buildings around it
+pub const DESIRABILITY: &str = "desirability";
+// how many services reach each tile
+pub const COVERAGE: &str = "service coverage";
+// which road network a road or building belongs to
+pub const ROAD_NETWORKS: &str = "road networks";
+pub const FIRE_RISK: &str = "fire risk";
+pub const POPULATION: &str = "population";
+
+// numbers per tile on top of the map, and how to show them as an overlay
+pub struct Layer {
+    pub name:   &'static str,
+    pub scale:  Scale,
+    pub values: Grid<i32>,
+}
+
+// the tiles of the map, and named layers of numbers per tile on top of them
+pub struct Map {
+    tiles:  Grid<Tile>,
+    layers: Vec<Layer>,
+}
+
+impl path::Map for &Map {
+    type Node = Coord; 
+    fn neighbours(&self, n: Self::Node) -> Vec<Self::Node> {
+        let mut neighbours: Vec<Self::Node> = vec![];
+        for p in von_neumann_neighbours(n).iter() {
+            let t = self.tiles.get(*p);
+            if let Some(tile) = t {
+                if (!tile.blocked && !tile.rubble && tile.terrain == Terrain::Ground) || tile.road {
+                    neighbours.push(*p);
+                }
+            }
+        }
+        neighbours
+    }
+
+    fn g(&self, _n: Self::Node, _neighbour: Self::Node) -> i64 { 1 }
+
+    fn h(&self, n: Self::Node, goal: Self::Node) -> i64 {
+        let dx = goal.x - n.x;
+        let dy = goal.y - n.y;
+        (dx.abs() + dy.abs()) as i64
+    }
+}
+
+// the map as roads see it when bridges may be built: from land a road can
+// jump straight across a short stretch of water to the land on the other side.
+// steps are weighed by what a tile of road and a tile of bridge cost
+struct Bridging<'a> {
+    map:    &'a Map,
+    road:   i64,
+    bridge: i64,
+}
+
+impl<'a> path::Map for Bridging<'a> {
+    type Node = Coord;
+    fn neighbours(&self, n: Self::Node) -> Vec<Self::Node> {
+        let mut neighbours = self.map.neighbours(n);
+        for &d in von_neumann_neighbours(Coord::new(0, 0)).iter() {
+            match self.map.across(n, d) {
+                Some((p, k)) if k > 0 => match self.map.get(&p) {
+                    Some(t) if (!t.blocked || t.road) && !t.rubble => neighbours.push(p),
+                    _ => {},
+                },
+                _ => {},
+            }
+        }
+        neighbours
+    }
+
+    // a step lands on a road tile, after a bridge tile for every tile of water
+    fn g(&self, n: Self::Node, neighbour: Self::Node) -> i64 {
+        self.road + (manhattan(n, neighbour) - 1) as i64 * self.bridge
+    }
+
+    fn h(&self, n: Self::Node, goal: Self::Node) -> i64 {
+        manhattan(n, goal) as i64 * self.road.min(self.bridge)
+    }
+}
+
+impl Map {
+    pub fn new(tiles: Grid<Tile>) -> Self {
+        Map{ tiles, layers: vec![] }.with_layer(DESIRABILITY, Scale::Diverging{ max: 20 })
+    }
+
+    // adds a layer, or starts an existing one over, every tile at zero
+    pub fn with_layer(mut self, name: &'static str, scale: Scale) -> Self {
+        let values = Grid::new(self.dimxy(), 0);
+        match self.layers.iter_mut().find(|l| l.name == name) {
+            Some(layer) => *layer = Layer{ name, scale, values },
+            None => self.layers.push(Layer{ name, scale, values }),
+        }
+        self
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Grid<i32>> {
+        self.layers.iter().find(|l| l.name == name).map(|l| &l.values)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut Grid<i32>> {
+        self.layers.iter_mut().find(|l| l.name == name).map(|l| &mut l.values)
+    }
+
+    // the value of a layer at c, zero off the map or without the layer
+    pub fn value(&self, name: &str, c: Coord) -> i32 {
+        self.layer(name).and_then(|l| l.get(c)).cloned().unwrap_or(0)
+    }
+
+    pub fn dimxy(&self) -> Coord {
+        self.tiles.dimxy()
+    }
+
+    pub fn get(&self, c: &Coord) -> Option<&Tile> {
+        self.tiles.get(*c)
+    }
+
+    pub fn desirability(&self, c: Coord) -> i32 {
+        self.value(DESIRABILITY, c)
+    }
+
+    fn get_mut(&mut self, c: &Coord) -> Option<&mut Tile> {
+        self.tiles.get_mut(*c)
+    }
+
+    fn set(&mut self, c: Coord, t: Tile) {
+        self.tiles.set(c, t);
+    }
+
+    // a river winding down the map around column x, narrow enough to bridge
+    pub fn with_river(mut self, x: i32) -> Self {
+        for y in 0..self.dimxy().y {
+            let river = x + (y / 6) % 2;
+            for x in river..river+3 {
+                self.set(Coord::new(x, y), Tile::water());
+            }
+        }
+        self
+    }
+
+    // the land reached going from c in direction d over at most BRIDGE_SPAN
+    // tiles of open water or bridge, and how many of those lie in between
+    fn across(&self, c: Coord, d: Coord) -> Option<(Coord, i32)> {
+        for k in 1..=BRIDGE_SPAN+1 {
+            let p = Coord::new(c.x + k*d.x, c.y + k*d.y);
+            match self.get(&p) {
+                Some(t) if t.terrain == Terrain::Water && (!t.blocked || t.road) => continue,
+                Some(t) if t.terrain == Terrain::Ground => return Some((p, k - 1)),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // whether a bridge over the water at c would reach land on both ends
+    // within BRIDGE_SPAN tiles, across or along the map
+    fn bridgeable(&self, c: Coord) -> bool {
+        [Coord::new(1, 0), Coord::new(0, 1)].iter().any(|d| {
+            match (self.across(c, *d), self.across(c, Coord::new(-d.x, -d.y))) {
+                (Some((_, a)), Some((_, b))) => a + b < BRIDGE_SPAN,
+                _ => false,
+            }
+        })
+    }
+
+    // whether any tile within radius of the building has the given terrain
+    pub fn near_terrain(&self, b: &Building, terrain: Terrain, radius: i32) -> bool {
+        for c in b.tiles() {
+            for y in c.y-radius..=c.y+radius {
+                for x in c.x-radius..=c.x+radius {
+                    if self.get(&Coord::new(x, y)).map_or(false, |t| t.terrain == terrain) {
+                        return true
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+pub fn make_map(dimxy: Coord) -> Map {
+    Map::new(Grid::from_fn(dimxy, |c| {
+        if c.x > (2*dimxy.x/3) {
+            Tile::water()
+        } else {
+            Tile::empty()
+        }
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behavior::Idle;
+
+    // a 10x8 map of open ground and a single 2x2 building type costing 10
+    fn types() -> Vec<Building> {
+        vec![Building::new_type(0usize, Coord::new(2,2), Resources(vec![10]), WHITE, Idle)]
+    }
+
+    fn game(types: &[Building]) -> Game<'_> {
+        let map = Map::new(Grid::new(Coord::new(10, 8), Tile::empty()));
+        let mut game = Game::new(types, 1usize, Resources(vec![1]), map);
+        game.resources.set(0usize, 100);
+        game
+    }
+
+    fn at(types: &[Building], x: i32, y: i32) -> Building {
+        types[0].clone().new_token(Coord::new(x, y))
+    }
+
+    #[test]
+    fn fits_flush_with_the_edges() {
+        let types = types();
+        let mut game = game(&types);
+        assert!(game.place(at(&types, 8, 6)).is_ok());
+        assert!(game.place(at(&types, 0, 0)).is_ok());
+    }
+
+    #[test]
+    fn one_tile_past_the_edges_is_out_of_bounds() {
+        let types = types();
+        let mut game = game(&types);
+        assert_eq!(game.place(at(&types, 9, 6)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.place(at(&types, 8, 7)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.place(at(&types, -1, 0)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.place(at(&types, 0, -1)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.resources.get(0usize), 100);
+    }
+
+    #[test]
+    fn water_is_the_wrong_terrain() {
+        let types = types();
+        let mut game = game(&types);
+        game.map.set(Coord::new(3, 3), Tile::water());
+        assert_eq!(game.place(at(&types, 2, 2)).err(), Some(PlacementError::WrongTerrain));
+    }
+
+    #[test]
+    fn occupied_and_rubble_tiles_are_blocked() {
+        let types = types();
+        let mut game = game(&types);
+        assert!(game.place(at(&types, 0, 0)).is_ok());
+        assert_eq!(game.place(at(&types, 1, 1)).err(), Some(PlacementError::Blocked));
+        game.map.get_mut(&Coord::new(5, 5)).unwrap().rubble = true;
+        assert_eq!(game.place(at(&types, 4, 4)).err(), Some(PlacementError::Blocked));
+    }
+
+    #[test]
+    fn unaffordable_leaves_the_stock_alone() {
+        let types = types();
+        let mut game = game(&types);
+        game.resources.set(0usize, 5);
+        match game.place(at(&types, 0, 0)) {
+            Err(PlacementError::Unaffordable(_)) => {},
+            other => panic!("expected unaffordable, got {:?}", other.err()),
+        }
+        assert_eq!(game.resources.get(0usize), 5);
+        assert_eq!(game.building_at(Coord::new(0, 0)), None);
+    }
+
+    #[test]
+    fn road_sites_at_the_edges() {
+        let types = types();
+        let mut game = game(&types);
+        game.map.set(Coord::new(3, 3), Tile::water());
+        assert!(game.place_road(Coord::new(9, 7)).is_ok());
+        assert_eq!(game.place_road(Coord::new(10, 7)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.place_road(Coord::new(-1, 0)).err(), Some(PlacementError::OutOfBounds));
+        assert_eq!(game.place_road(Coord::new(3, 3)).err(), Some(PlacementError::WrongTerrain));
+        assert_eq!(game.place_road(Coord::new(9, 7)).err(), Some(PlacementError::Blocked));
+    }
+
+    fn upkeep_game(types: &mut [Building], stock: i32) -> Game<'_> {
+        types[0].upkeep = Resources(vec![10]);
+        let mut game = game(types);
+        game.scenario = Some(Scenario::new("", &["money"], &["house"])
+            .with_population(1000).with_never_bankrupt(0usize));
+        assert!(game.place(at(types, 0, 0)).is_ok());
+        game.resources.set(0usize, stock);
+        for _ in 0..UPKEEP_PERIOD {
+            game.update_buildings();
+        }
+        game
+    }
+
+    #[test]
+    fn paying_down_to_nothing_is_not_bankrupt() {
+        let mut types = types();
+        let game = upkeep_game(&mut types, 10);
+        assert_eq!(game.resources.get(0usize), 0);
+        assert_eq!(game.scenario.as_ref().map(|s| s.outcome()), Some(Outcome::Playing));
+    }
+
+    #[test]
+    fn upkeep_we_cannot_pay_is_bankrupt() {
+        let mut types = types();
+        let game = upkeep_game(&mut types, 9);
+        assert_eq!(game.scenario.as_ref().map(|s| s.outcome()), Some(Outcome::Defeat));
+    }
+
+    #[test]
+    fn unavailable_types_are_locked() {
+        let types = types();
+        let mut game = game(&types).with_available(&[]);
+        assert_eq!(game.check_placement(&at(&types, 0, 0)).err(), Some(PlacementError::Locked));
+        assert_eq!(game.place(at(&types, 0, 0)).err(), Some(PlacementError::Locked));
+        assert_eq!(game.resources.get(0usize), 100);
+    }
+
+    #[test]
+    fn types_still_to_unlock_are_locked() {
+        let mut types = types();
+        types[0] = types[0].clone().with_requirement(Requirement::Population(10));
+        let mut game = game(&types);
+        assert_eq!(game.place(at(&types, 0, 0)).err(), Some(PlacementError::Locked));
+        game.unlocked[0] = true;
+        assert!(game.place(at(&types, 0, 0)).is_ok());
+    }
+
+    // pays out when torn down
+    #[derive(Clone)]
+    struct Salvage;
+
+    impl BuildingBehavior for Salvage {
+        fn update(&mut self, _: &mut Context) {}
+
+        fn on_demolish(&mut self, ctx: &mut Context) {
+            ctx.produced = Resources(vec![50]);
+        }
+    }
+
+    #[test]
+    fn ruins_pay_no_salvage_and_book_lost_goods() {
+        let types = vec![Building::new_type(0usize, Coord::new(2,2), Resources(vec![10]), WHITE, Salvage)
+            .with_storage(Resources(vec![40]))];
+        let mut game = game(&types);
+        let e = game.place(at(&types, 0, 0)).unwrap();
+        game.resources.set(0usize, 30);
+        game.stores.get_mut(e).unwrap().put(0, 30);
+        game.ruin(e);
+        assert_eq!(game.resources.get(0usize), 0);
+        assert_eq!(game.ledger.total(0, Category::Losses), -30);
+        assert!(game.map.get(&Coord::new(1, 1)).unwrap().rubble);
+    }
+
+    #[test]
+    fn clearing_rubble_clears_the_whole_ruin_for_a_price() {
+        let types = types();
+        let mut game = game(&types);
+        let e = game.place(at(&types, 0, 0)).unwrap();
+        game.ruin(e);
+        game.resources.set(0usize, 3);
+        assert!(game.clear_rubble(Coord::new(1, 1)).is_err());
+        assert!(game.map.get(&Coord::new(0, 0)).unwrap().rubble);
+        game.resources.set(0usize, 14);
+        assert!(game.clear_rubble(Coord::new(1, 1)).is_ok());
+        assert_eq!(game.resources.get(0usize), 10);
+        assert!(game.map.tiles.iter().all(|(_, t)| !t.rubble));
+        assert!(game.place(at(&types, 0, 0)).is_ok());
+    }
+
+    // a map with a strait of the given width running down column 3
+    fn strait(width: i32) -> Map {
+        Map::new(Grid::from_fn(Coord::new(12, 6), |c| {
+            if c.x >= 3 && c.x < 3 + width { Tile::water() } else { Tile::empty() }
+        }))
+    }
+
+    #[test]
+    fn bridges_only_span_short_water() {
+        let types = types();
+        let mut game = game(&types);
+        game.map = strait(BRIDGE_SPAN);
+        assert_eq!(game.check_road_site(Coord::new(3, 2)).err(), Some(PlacementError::WrongTerrain));
+        game.bridging = true;
+        for x in 3..3+BRIDGE_SPAN {
+            assert!(game.check_road_site(Coord::new(x, 2)).is_ok());
+        }
+        game.map = strait(BRIDGE_SPAN + 1);
+        assert_eq!(game.check_road_site(Coord::new(4, 2)).err(), Some(PlacementError::WrongTerrain));
+    }
+
+    #[test]
+    fn every_map_layer_is_an_overlay() {
+        let types = types();
+        let mut game = game(&types);
+        game.map = Map::new(Grid::new(Coord::new(10, 8), Tile::empty())).with_layer("fertility", Scale::Categorical);
+        let mut names = vec![];
+        for _ in 0..game.map.layers().len() + 1 {
+            game.overlay = game.next_overlay();
+            names.push(game.overlay.name());
+        }
+        assert_eq!(names, vec![DESIRABILITY, "fertility", "terrain"]);
+    }
+
+    #[test]
+    fn routes_weigh_bridges_by_their_cost() {
+        let types = types();
+        // the bridge is 4 steps, the way round over the bottom row 14
+        for &(cost, crosses) in [(5, true), (100, false)].iter() {
+            let mut game = game(&types).with_bridge_cost(Resources(vec![cost]));
+            // a strait down column 3 with a ford across the bottom row
+            game.map = Map::new(Grid::from_fn(Coord::new(8, 6), |c| {
+                if c.x == 3 && c.y < 5 { Tile::water() } else { Tile::empty() }
+            }));
+            game.bridging = true;
+            let route = game.route(Coord::new(1, 0), Coord::new(5, 0)).unwrap();
+            assert_eq!(route.iter().all(|c| c.y == 0), crosses);
+        }
+    }
+
+    #[test]
+    fn protection_reaches_only_as_far_as_its_radius() {
+        let mut types = types();
+        types.push(types[0].clone().with_prevention(EventKind::Fire, 4));
+        types[1].btype = 1;
+        let mut game = Game::new(&types, 1usize, Resources(vec![1]), Map::new(Grid::new(Coord::new(20, 8), Tile::empty())));
+        game.resources.set(0usize, 100);
+        let near = game.place(types[0].clone().new_token(Coord::new(0, 0))).unwrap();
+        let far = game.place(types[0].clone().new_token(Coord::new(16, 0))).unwrap();
+        game.place(types[1].clone().new_token(Coord::new(4, 0))).unwrap();
+        assert!(game.is_protected(near, EventKind::Fire));
+        assert!(!game.is_protected(far, EventKind::Fire));
+        assert!(!game.is_protected(near, EventKind::Plague));
+    }
+
+    #[test]
+    fn salvage_is_not_booked_as_income() {
+        let types = vec![Building::new_type(0usize, Coord::new(2,2), Resources(vec![10]), WHITE, Salvage)
+            .with_income(Category::Taxes)];
+        let mut game = game(&types);
+        let e = game.place(at(&types, 0, 0)).unwrap();
+        game.demolish(e);
+        assert_eq!(game.ledger.total(0, Category::Production), 50);
+        assert_eq!(game.ledger.total(0, Category::Taxes), 0);
+    }
+}
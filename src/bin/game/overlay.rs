@@ -0,0 +1,50 @@
+use tcod::colors::*;
+
+// what the map background shows: the terrain, or one of the named
+// layers of the map with its numbers per tile coloured by its scale
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overlay {
+    Terrain,
+    Layer(&'static str, Scale),
+}
+
+impl Overlay {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overlay::Terrain        => "terrain",
+            Overlay::Layer(name, _) => name,
+        }
+    }
+
+    pub fn scale(&self) -> Scale {
+        match self {
+            Overlay::Terrain         => Scale::Categorical,
+            Overlay::Layer(_, scale) => *scale,
+        }
+    }
+}
+
+// how the numbers of a layer turn into background colours
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    // from the base colour at zero to the given colour at max
+    Sequential{ max: i32, to: Color },
+    // red below zero and green above, strongest at max either way
+    Diverging{ max: i32 },
+    // a colour of its own for every number but zero
+    Categorical,
+}
+
+const CATEGORIES: [Color; 6] = [LIGHT_BLUE, LIGHT_GREEN, LIGHT_YELLOW, LIGHT_MAGENTA, LIGHT_CYAN, LIGHT_ORANGE];
+
+impl Scale {
+    pub fn color(&self, base: Color, v: i32) -> Color {
+        let strength = |max: i32| (v.abs() as f32 / max.max(1) as f32).min(1.0);
+        match *self {
+            Scale::Sequential{ max, to } => lerp(base, to, strength(max)),
+            Scale::Diverging{ max } => lerp(base, if v < 0 { RED } else { GREEN }, strength(max)),
+            Scale::Categorical if v == 0 => base,
+            Scale::Categorical => CATEGORIES[(v - 1).rem_euclid(CATEGORIES.len() as i32) as usize],
+        }
+    }
+}
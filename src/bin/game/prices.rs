@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use crate::game::{Resources, Stock};
+use crate::game::ledger::Ledger;
+use crate::game::trade::Partner;
+
+// prices move once every this many ticks
+pub const PRICE_PERIOD: i32 = 100;
+// how many past prices are kept for the graphs
+pub const HISTORY: usize = 60;
+
+// what every good is worth in the currency; a good gets dearer the fewer
+// of it we have compared to how much of it we use, and cheaper the more
+pub struct Prices {
+    currency: usize,
+    // the price of a good when we hold as much as we use over a ledger window
+    base:     Resources,
+    current:  Resources,
+    // past prices per good, oldest first
+    history:  Vec<VecDeque<i32>>,
+}
+
+impl Prices {
+    #[allow(dead_code)]
+    pub fn new<C>(currency: C, base: Resources) -> Self
+    where C: std::convert::Into<usize>
+    {
+        let currency = currency.into();
+        let mut base = base;
+        // the currency is what everything else is counted in
+        base.set(currency, 1);
+        Prices{
+            currency: currency,
+            current:  base.clone(),
+            history:  vec![VecDeque::new(); base.len()],
+            base:     base,
+        }
+    }
+
+    pub fn get(&self, r: usize) -> i32 {
+        self.current.get(r)
+    }
+
+    pub fn base(&self, r: usize) -> i32 {
+        self.base.get(r)
+    }
+
+    pub fn history(&self, r: usize) -> &VecDeque<i32> {
+        &self.history[r]
+    }
+
+    // what a stock of goods is worth at today's prices
+    pub fn value(&self, stock: &Stock) -> i32 {
+        (0..stock.len()).map(|r| stock.get(r).saturating_mul(self.get(r))).fold(0, i32::saturating_add)
+    }
+
+    // moves every price a step towards what stock and consumption say it should be
+    pub fn update(&mut self, stock: &Stock, ledger: &Ledger) {
+        for r in 0..self.current.len() {
+            if r != self.currency {
+                let (base, old) = (self.base.get(r), self.current.get(r));
+                let used = ledger.spent(r).max(1);
+                let target = (base * 2 * used / (used + stock.get(r))).max(base / 4).max(1);
+                let step = match (target - old) / 4 {
+                    0 => (target - old).signum(),
+                    step => step,
+                };
+                self.current.set(r, old + step);
+            }
+            let h = &mut self.history[r];
+            h.push_back(self.current.get(r));
+            if h.len() > HISTORY {
+                h.pop_front();
+            }
+        }
+    }
+
+    // partner prices are what they ask and pay at our base prices,
+    // and move along with ours
+    pub fn adjust(&self, partner: &Partner) -> Partner {
+        let mut adjusted = partner.clone();
+        for r in 0..self.current.len() {
+            adjusted.sells.set(r, self.scale(r, partner.sells.get(r)));
+            adjusted.buys.set(r, self.scale(r, partner.buys.get(r)));
+        }
+        adjusted
+    }
+
+    fn scale(&self, r: usize, price: i32) -> i32 {
+        if price == 0 {
+            return 0
+        }
+        (price * self.current.get(r) / self.base.get(r).max(1)).max(1)
+    }
+}
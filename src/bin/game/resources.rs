@@ -0,0 +1,169 @@
+use std::fmt;
+
+use crate::game::resource_glyph;
+
+// an amount of every resource in the game, used for costs and production
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resources(pub Vec<i32>);
+
+impl Resources {
+    pub fn get<T>(&self, r: T) -> i32 
+    where T: std::convert::Into<usize> 
+    {
+        self.0[r.into()]
+    }
+
+    pub fn set<T>(&mut self, r: T, v: i32)
+    where T: std::convert::Into<usize> 
+    {
+        self.0[r.into()] = v;
+    }
+
+    pub fn update<T>(&mut self, r: T, v: i32)
+    where T: std::convert::Into<usize> 
+    {
+        let rusize = r.into();
+        self.0[rusize] = self.0[rusize].saturating_add(v);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|&x| x == 0)
+    }
+
+    pub fn plus(&self, other: &Resources) -> Resources {
+        assert_eq!(self.len(), other.len(), "resources of wrong length");
+        Resources(self.0.iter().zip(other.0.iter()).map(|(x, y)| x.saturating_add(*y)).collect::<Vec<i32>>())
+    }
+
+    pub fn times(&self, n: i32) -> Resources {
+        Resources(self.0.iter().map(|x| x.saturating_mul(n)).collect::<Vec<i32>>())
+    }
+}
+
+// how much of a single resource we are missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shortfall {
+    pub resource: usize,
+    pub missing:  i32,
+}
+
+// returned when the stock does not cover a cost, listing every resource short
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Insufficient(pub Vec<Shortfall>);
+
+impl fmt::Display for Insufficient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing")?;
+        for (i, s) in self.0.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}{}", sep, resource_glyph(s.resource), s.missing)?;
+        }
+        Ok(())
+    }
+}
+
+// the resources the player owns; amounts never go below zero
+// or above the storage cap set for each resource
+#[derive(Clone, Debug)]
+pub struct Stock {
+    amounts: Vec<i32>,
+    caps:    Vec<i32>,
+}
+
+impl Stock {
+    pub fn new(num_resources: usize) -> Self {
+        Stock{
+            amounts: vec![0; num_resources],
+            caps:    vec![std::i32::MAX; num_resources],
+        }
+    }
+
+    // resource vectors from the game must match the number of resources,
+    // anything else is a bug in how the game was set up
+    fn check_len(&self, other: &Resources) {
+        assert_eq!(self.amounts.len(), other.len(), "resources of wrong length");
+    }
+
+    pub fn len(&self) -> usize {
+        self.amounts.len()
+    }
+
+    pub fn get<T>(&self, r: T) -> i32 
+    where T: std::convert::Into<usize> 
+    {
+        self.amounts[r.into()]
+    }
+
+    pub fn cap<T>(&self, r: T) -> i32 
+    where T: std::convert::Into<usize> 
+    {
+        self.caps[r.into()]
+    }
+
+    pub fn set<T>(&mut self, r: T, v: i32)
+    where T: std::convert::Into<usize> 
+    {
+        let rusize = r.into();
+        self.amounts[rusize] = v.max(0).min(self.caps[rusize]);
+    }
+
+    // also clamps the current amount to the new cap
+    pub fn set_cap<T>(&mut self, r: T, cap: i32)
+    where T: std::convert::Into<usize> 
+    {
+        let rusize = r.into();
+        self.caps[rusize] = cap.max(0);
+        self.set(rusize, self.amounts[rusize]);
+    }
+
+    // adds (or removes) v, returning how much actually changed
+    pub fn update<T>(&mut self, r: T, v: i32) -> i32
+    where T: std::convert::Into<usize> 
+    {
+        let rusize = r.into();
+        let old = self.amounts[rusize];
+        self.set(rusize, old.saturating_add(v));
+        self.amounts[rusize] - old
+    }
+
+    pub fn covers(&self, cost: &Resources) -> bool {
+        self.check(cost).is_ok()
+    }
+
+    pub fn check(&self, cost: &Resources) -> Result<(), Insufficient> {
+        self.check_len(cost);
+        let shortfalls: Vec<Shortfall> = self.amounts.iter().zip(cost.0.iter()).enumerate()
+            .filter(|(_, (&x, &y))| x < y)
+            .map(|(r, (&x, &y))| Shortfall{ resource: r, missing: y - x })
+            .collect();
+        if shortfalls.is_empty() {
+            Ok(())
+        } else {
+            Err(Insufficient(shortfalls))
+        }
+    }
+
+    // pays the full cost, or nothing at all if we are short on anything
+    pub fn try_spend(&mut self, cost: &Resources) -> Result<(), Insufficient> {
+        self.check(cost)?;
+        for (r, &v) in cost.0.iter().enumerate() {
+            self.update(r, -v);
+        }
+        Ok(())
+    }
+
+    // adds income up to the storage caps, returning what did not fit
+    pub fn deposit(&mut self, income: &Resources) -> Resources {
+        self.check_len(income);
+        let mut rest = income.clone();
+        for (r, &v) in income.0.iter().enumerate() {
+            let added = self.update(r, v);
+            rest.0[r] = v - added;
+        }
+        rest
+    }
+}
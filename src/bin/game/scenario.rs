@@ -0,0 +1,142 @@
+use crate::game::Stock;
+
+// something the player has to reach, or keep up, to win a scenario
+#[derive(Clone, Debug)]
+pub enum Objective {
+    Population(i32),
+    Stockpile{ resource: usize, amount: i32 },
+    // at least one building of the type standing by the given tick
+    BuildBy{ btype: usize, tick: i32 },
+    // lost as soon as we cannot pay what we owe in the resource;
+    // spending it all is fine
+    NeverBankrupt(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Met,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    Victory,
+    Defeat,
+}
+
+// the state of the game the objectives are checked against
+pub struct Progress<'p> {
+    pub tick:       i32,
+    pub population: i32,
+    pub stock:      &'p Stock,
+    // the resources a payment came up short on since the last check
+    pub short:      &'p [bool],
+    // how many buildings of each type are standing
+    pub built:      Vec<i32>,
+}
+
+// a map to play with a set of objectives; the scenario is won once all
+// objectives are met at the same time, and lost once any of them fails
+#[derive(Clone)]
+pub struct Scenario {
+    pub title:  String,
+    resources:  Vec<String>,
+    buildings:  Vec<String>,
+    objectives: Vec<(Objective, Status)>,
+    outcome:    Outcome,
+}
+
+impl Scenario {
+    pub fn new(title: &str, resources: &[&str], buildings: &[&str]) -> Self {
+        Scenario{
+            title:      title.to_string(),
+            resources:  resources.iter().map(|r| r.to_string()).collect(),
+            buildings:  buildings.iter().map(|b| b.to_string()).collect(),
+            objectives: vec![],
+            outcome:    Outcome::Playing,
+        }
+    }
+
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objectives.push((objective, Status::Pending));
+        self
+    }
+
+    pub fn with_population(self, population: i32) -> Self {
+        self.with_objective(Objective::Population(population))
+    }
+
+    pub fn with_stockpile<T>(self, resource: T, amount: i32) -> Self
+    where T: std::convert::Into<usize>
+    {
+        self.with_objective(Objective::Stockpile{ resource: resource.into(), amount })
+    }
+
+    pub fn with_building_by<T>(self, btype: T, tick: i32) -> Self
+    where T: std::convert::Into<usize>
+    {
+        self.with_objective(Objective::BuildBy{ btype: btype.into(), tick })
+    }
+
+    pub fn with_never_bankrupt<T>(self, resource: T) -> Self
+    where T: std::convert::Into<usize>
+    {
+        self.with_objective(Objective::NeverBankrupt(resource.into()))
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    // a line of text per objective with how it is going
+    pub fn goals(&self) -> Vec<(String, Status)> {
+        self.objectives.iter().map(|(o, s)| (self.describe(o), *s)).collect()
+    }
+
+    fn describe(&self, objective: &Objective) -> String {
+        let name = |names: &Vec<String>, i: usize| names.get(i).cloned().unwrap_or_else(|| format!("#{}", i));
+        match *objective {
+            Objective::Population(n) => format!("reach a population of {}", n),
+            Objective::Stockpile{ resource, amount } => format!("stockpile {} {}", amount, name(&self.resources, resource)),
+            Objective::BuildBy{ btype, tick } => format!("build a {} by tick {}", name(&self.buildings, btype), tick),
+            Objective::NeverBankrupt(r) => format!("never run out of {}", name(&self.resources, r)),
+        }
+    }
+
+    // updates every objective and decides the outcome once it is clear
+    pub fn check(&mut self, p: &Progress) -> Outcome {
+        if self.outcome != Outcome::Playing {
+            return self.outcome
+        }
+        for (objective, status) in self.objectives.iter_mut() {
+            *status = match *objective {
+                Objective::Population(n) => if p.population >= n { Status::Met } else { Status::Pending },
+                Objective::Stockpile{ resource, amount } => {
+                    if p.stock.get(resource) >= amount { Status::Met } else { Status::Pending }
+                },
+                // once built in time it stays met, even if torn down later
+                Objective::BuildBy{ .. } if *status == Status::Met => Status::Met,
+                Objective::BuildBy{ btype, tick } => {
+                    if p.built.get(btype).map_or(false, |&n| n > 0) && p.tick <= tick {
+                        Status::Met
+                    } else if p.tick > tick {
+                        Status::Failed
+                    } else {
+                        Status::Pending
+                    }
+                },
+                Objective::NeverBankrupt(r) => {
+                    if p.short.get(r).cloned().unwrap_or(false) { Status::Failed } else { Status::Met }
+                },
+            };
+        }
+        if self.objectives.iter().any(|(_, s)| *s == Status::Failed) {
+            self.outcome = Outcome::Defeat;
+        } else if !self.objectives.is_empty() && self.objectives.iter().all(|(_, s)| *s == Status::Met) {
+            self.outcome = Outcome::Victory;
+        }
+        self.outcome
+    }
+}
@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, Scope, AST};
+use rhai::module_resolvers::DummyModuleResolver;
+
+use crate::game::Coord;
+use crate::game::Tile;
+use crate::game::Terrain;
+use crate::game::NEIGHBOUR_RADIUS;
+use crate::game::behavior::{BuildingBehavior, Context};
+use crate::game::grid::Grid;
+
+// limits so a broken script cannot hang the game or eat all memory
+const MAX_OPERATIONS: u64 = 20_000;
+const MAX_CALL_LEVELS: usize = 16;
+const MAX_EXPR_DEPTH: usize = 32;
+const MAX_STRING_SIZE: usize = 256;
+const MAX_COLLECTION_SIZE: usize = 256;
+
+// the functions a script may define, each taking the api as ctx
+const ON_PLACE: &str = "on_place";
+const ON_TICK: &str = "on_tick";
+const ON_DEMOLISH: &str = "on_demolish";
+
+// an engine without access to files, modules or the console,
+// and with everything it can do bounded
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|_| ());
+    engine.on_debug(|_, _, _| ());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    engine.register_type_with_name::<Api>("Building");
+    engine.register_get("tick", |api: &mut Api| api.0.borrow().tick);
+    engine.register_get("x", |api: &mut Api| api.0.borrow().entrance.x as i64);
+    engine.register_get("y", |api: &mut Api| api.0.borrow().entrance.y as i64);
+    engine.register_fn("stock", Api::stock);
+    engine.register_fn("produce", Api::produce);
+    engine.register_fn("neighbours", Api::neighbours);
+    engine.register_fn("terrain", Api::terrain);
+    engine.register_fn("is_road", Api::is_road);
+    engine.register_fn("is_built", Api::is_built);
+    engine.register_fn("set_terrain", Api::set_terrain);
+    engine.register_fn("remember", Api::remember);
+    engine.register_fn("recall", Api::recall);
+    engine
+}
+
+fn terrain_name(terrain: Terrain) -> &'static str {
+    match terrain {
+        Terrain::Ground => "ground",
+        Terrain::Water  => "water",
+    }
+}
+
+fn terrain_named(name: &str) -> Option<Terrain> {
+    match name {
+        "ground" => Some(Terrain::Ground),
+        "water"  => Some(Terrain::Water),
+        _ => None,
+    }
+}
+
+// what a script gets to see of the game: a copy of the stock, counts of
+// neighbouring building types and the tiles within reach of the building.
+// anything it changes is handed back to the game when the call is done
+struct Snapshot {
+    resources:  Rc<Vec<String>>,
+    buildings:  Rc<Vec<String>>,
+    tick:       i64,
+    entrance:   Coord,
+    stock:      Vec<i64>,
+    neighbours: Vec<i64>,
+    origin:     Coord,
+    area:       Grid<Option<Tile>>,
+    produced:   Vec<i64>,
+    edits:      Vec<(Coord, Terrain)>,
+    memory:     rhai::Map,
+}
+
+// the handle scripts call into, shared with the engine for one call
+#[derive(Clone)]
+struct Api(Rc<RefCell<Snapshot>>);
+
+// a map coordinate from what a script passed, if it is one at all
+fn coord(x: i64, y: i64) -> Option<Coord> {
+    Some(Coord::new(i32::try_from(x).ok()?, i32::try_from(y).ok()?))
+}
+
+impl Api {
+    fn tile(&self, x: i64, y: i64) -> Option<Tile> {
+        let s = self.0.borrow();
+        let c = coord(x, y)?;
+        let c = Coord::new(c.x.checked_sub(s.origin.x)?, c.y.checked_sub(s.origin.y)?);
+        s.area.get(c).cloned().flatten()
+    }
+
+    fn resource(&self, name: &str) -> Option<usize> {
+        self.0.borrow().resources.iter().position(|r| r == name)
+    }
+
+    fn stock(api: &mut Api, name: &str) -> i64 {
+        match api.resource(name) {
+            Some(r) => api.0.borrow().stock[r],
+            None => 0,
+        }
+    }
+
+    // negative amounts consume what is in stock
+    fn produce(api: &mut Api, name: &str, amount: i64) {
+        if let Some(r) = api.resource(name) {
+            let mut s = api.0.borrow_mut();
+            s.produced[r] = s.produced[r].saturating_add(amount);
+        }
+    }
+
+    fn neighbours(api: &mut Api, name: &str) -> i64 {
+        let s = api.0.borrow();
+        match s.buildings.iter().position(|b| b == name) {
+            Some(b) => s.neighbours[b],
+            None => 0,
+        }
+    }
+
+    // empty outside of what the building can see
+    fn terrain(api: &mut Api, x: i64, y: i64) -> String {
+        api.tile(x, y).map_or("", |t| terrain_name(t.terrain())).to_string()
+    }
+
+    fn is_road(api: &mut Api, x: i64, y: i64) -> bool {
+        api.tile(x, y).map_or(false, |t| t.is_road())
+    }
+
+    fn is_built(api: &mut Api, x: i64, y: i64) -> bool {
+        api.tile(x, y).map_or(false, |t| t.occupant().is_some())
+    }
+
+    // only within what the building can see
+    fn set_terrain(api: &mut Api, x: i64, y: i64, name: &str) {
+        if let (Some(_), Some(c), Some(terrain)) = (api.tile(x, y), coord(x, y), terrain_named(name)) {
+            api.0.borrow_mut().edits.push((c, terrain));
+        }
+    }
+
+    fn remember(api: &mut Api, key: &str, value: Dynamic) {
+        api.0.borrow_mut().memory.insert(key.into(), value);
+    }
+
+    fn recall(api: &mut Api, key: &str) -> Dynamic {
+        api.0.borrow().memory.get(key).cloned().unwrap_or(Dynamic::UNIT)
+    }
+}
+
+// a building behavior written in rhai; a script defines any of
+// on_place(ctx), on_tick(ctx) and on_demolish(ctx)
+#[derive(Clone)]
+pub struct Scripted {
+    engine:    Rc<Engine>,
+    ast:       Rc<AST>,
+    resources: Rc<Vec<String>>,
+    buildings: Rc<Vec<String>>,
+    // what this building remembered between calls
+    memory:    rhai::Map,
+    // the functions that failed once; they are not run again
+    failed:    Vec<&'static str>,
+}
+
+impl Scripted {
+    pub fn new(source: &str, resources: &[&str], buildings: &[&str]) -> Result<Self, String> {
+        let engine = sandboxed_engine();
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Ok(Scripted{
+            engine:    Rc::new(engine),
+            ast:       Rc::new(ast),
+            resources: Rc::new(resources.iter().map(|r| r.to_string()).collect()),
+            buildings: Rc::new(buildings.iter().map(|b| b.to_string()).collect()),
+            memory:    rhai::Map::new(),
+            failed:    vec![],
+        })
+    }
+
+    fn defines(&self, event: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == event && f.params.len() == 1)
+    }
+
+    fn snapshot(&self, ctx: &Context) -> Snapshot {
+        let b = ctx.building;
+        let origin = Coord::new(b.topleft.x - NEIGHBOUR_RADIUS, b.topleft.y - NEIGHBOUR_RADIUS);
+        let dimxy = Coord::new(b.footprint.dimxy.x + 2*NEIGHBOUR_RADIUS, b.footprint.dimxy.y + 2*NEIGHBOUR_RADIUS);
+        let mut neighbours = vec![0; self.buildings.len()];
+        for n in ctx.neighbours.iter() {
+            if let Some(count) = neighbours.get_mut(n.btype) {
+                *count += 1;
+            }
+        }
+        Snapshot{
+            resources:  self.resources.clone(),
+            buildings:  self.buildings.clone(),
+            tick:       ctx.tick as i64,
+            entrance:   b.entrance(),
+            stock:      (0..ctx.stock.len()).map(|r| ctx.stock.get(r) as i64).collect(),
+            neighbours: neighbours,
+            origin:     origin,
+            area:       Grid::from_fn(dimxy, |c| ctx.map.get(&Coord::new(origin.x + c.x, origin.y + c.y)).cloned()),
+            produced:   vec![0; ctx.produced.len()],
+            edits:      vec![],
+            memory:     self.memory.clone(),
+        }
+    }
+
+    fn call(&mut self, event: &'static str, ctx: &mut Context) {
+        if self.failed.contains(&event) || !self.defines(event) {
+            return
+        }
+        let api = Api(Rc::new(RefCell::new(self.snapshot(ctx))));
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, event, (api.clone(),));
+        if let Err(e) = result {
+            ctx.errors.push(format!("its {} script failed and was stopped: {}", event, e));
+            self.failed.push(event);
+            return
+        }
+        let s = api.0.borrow();
+        for (r, &v) in s.produced.iter().enumerate() {
+            ctx.produced.update(r, v.max(i32::MIN as i64).min(i32::MAX as i64) as i32);
+        }
+        ctx.edits.extend(s.edits.iter().cloned());
+        self.memory = s.memory.clone();
+    }
+}
+
+impl BuildingBehavior for Scripted {
+    fn update(&mut self, ctx: &mut Context) {
+        self.call(ON_TICK, ctx);
+    }
+
+    fn on_place(&mut self, ctx: &mut Context) {
+        self.call(ON_PLACE, ctx);
+    }
+
+    fn on_demolish(&mut self, ctx: &mut Context) {
+        self.call(ON_DEMOLISH, ctx);
+    }
+}
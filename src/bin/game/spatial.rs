@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::game::Coord;
+use crate::game::entity::Entity;
+
+// size of the square cells buildings are bucketed in
+const CELL: i32 = 8;
+
+struct Entry {
+    entity: Entity,
+    btype:  usize,
+    pos:    Coord,
+}
+
+// buckets buildings by position on a uniform grid, so range and nearest
+// queries only have to look at the cells around a point
+pub struct SpatialIndex {
+    cells: HashMap<(i32, i32), Vec<Entry>>,
+}
+
+fn cell_of(c: Coord) -> (i32, i32) {
+    (c.x.div_euclid(CELL), c.y.div_euclid(CELL))
+}
+
+fn distance(a: Coord, b: Coord) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        SpatialIndex{ cells: HashMap::new() }
+    }
+
+    // pos is the point distances are measured from, usually the entrance
+    pub fn insert(&mut self, entity: Entity, btype: usize, pos: Coord) {
+        self.cells.entry(cell_of(pos)).or_insert_with(Vec::new).push(Entry{ entity, btype, pos });
+    }
+
+    pub fn remove(&mut self, entity: Entity, pos: Coord) {
+        if let Some(entries) = self.cells.get_mut(&cell_of(pos)) {
+            entries.retain(|e| e.entity != entity);
+        }
+    }
+
+    // all entries in cells overlapping the square of radius r around c
+    fn around(&self, c: Coord, r: i32) -> impl Iterator<Item=&Entry> {
+        let (x0, y0) = cell_of(Coord::new(c.x - r, c.y - r));
+        let (x1, y1) = cell_of(Coord::new(c.x + r, c.y + r));
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    // buildings within manhattan distance radius of c, of the given type if any
+    pub fn within(&self, c: Coord, radius: i32, btype: Option<usize>) -> Vec<Entity> {
+        self.around(c, radius)
+            .filter(|e| btype.map_or(true, |t| t == e.btype) && distance(c, e.pos) <= radius)
+            .map(|e| e.entity)
+            .collect()
+    }
+
+    // the closest building of the given type to c, searching ring by ring of cells
+    pub fn nearest(&self, c: Coord, btype: usize) -> Option<(Entity, i32)> {
+        let max_ring = self.cells.keys().map(|&(x, y)| {
+            let (cx, cy) = cell_of(c);
+            std::cmp::max((x - cx).abs(), (y - cy).abs())
+        }).max()?;
+        let mut best: Option<(Entity, i32)> = None;
+        for ring in 0..=max_ring {
+            let r = ring * CELL;
+            for e in self.around(c, r).filter(|e| e.btype == btype) {
+                let d = distance(c, e.pos);
+                if best.map_or(true, |(_, bd)| d < bd) {
+                    best = Some((e.entity, d));
+                }
+            }
+            // anything in the next ring is at least this far away
+            if let Some((_, bd)) = best {
+                if bd <= r {
+                    break
+                }
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,111 @@
+use crate::game::Resources;
+
+// producers only deliver to storage with its entrance this close to theirs
+pub const STORAGE_RANGE: i32 = 12;
+// storage being emptied sends goods away once every this many ticks
+pub const EMPTY_PERIOD: i32 = 50;
+// and this many units of a good at a time
+pub const EMPTY_AMOUNT: i32 = 10;
+
+// what a storage building does with a good
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Accept,
+    // keeps what it has but takes no more
+    Reject,
+    // takes no more and sends what it has to other storage
+    Empty,
+}
+
+impl Setting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Setting::Accept => "accept",
+            Setting::Reject => "reject",
+            Setting::Empty  => "empty",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Setting::Accept => Setting::Reject,
+            Setting::Reject => Setting::Empty,
+            Setting::Empty  => Setting::Accept,
+        }
+    }
+}
+
+// the goods held by one placed storage building
+pub struct Store {
+    capacity:     Resources,
+    amounts:      Resources,
+    settings:     Vec<Setting>,
+    // the good picked in the storage panel
+    pub selected: usize,
+}
+
+impl Store {
+    pub fn new(capacity: &Resources) -> Self {
+        Store{
+            capacity: capacity.clone(),
+            amounts:  Resources(vec![0; capacity.len()]),
+            settings: vec![Setting::Accept; capacity.len()],
+            selected: capacity.0.iter().position(|&c| c > 0).unwrap_or(0),
+        }
+    }
+
+    pub fn amount(&self, r: usize) -> i32 {
+        self.amounts.get(r)
+    }
+
+    pub fn capacity(&self, r: usize) -> i32 {
+        self.capacity.get(r)
+    }
+
+    pub fn setting(&self, r: usize) -> Setting {
+        self.settings[r]
+    }
+
+    pub fn amounts(&self) -> &Resources {
+        &self.amounts
+    }
+
+    // how much more of a good the store takes right now
+    pub fn room(&self, r: usize) -> i32 {
+        if self.settings[r] != Setting::Accept {
+            return 0
+        }
+        self.capacity.get(r) - self.amounts.get(r)
+    }
+
+    // returns how much was put in
+    pub fn put(&mut self, r: usize, n: i32) -> i32 {
+        let n = n.min(self.room(r)).max(0);
+        self.amounts.update(r, n);
+        n
+    }
+
+    // returns how much was taken out
+    pub fn take(&mut self, r: usize, n: i32) -> i32 {
+        let n = n.min(self.amounts.get(r)).max(0);
+        self.amounts.update(r, -n);
+        n
+    }
+
+    pub fn cycle_setting(&mut self, r: usize) {
+        self.settings[r] = self.settings[r].next();
+    }
+
+    // moves the selection in the panel by d, over the goods the store can hold
+    pub fn select(&mut self, d: i32) {
+        let n = self.capacity.len() as i32;
+        let mut s = self.selected as i32;
+        for _ in 0..n {
+            s = (s + d).rem_euclid(n);
+            if self.capacity.get(s as usize) > 0 {
+                break
+            }
+        }
+        self.selected = s as usize;
+    }
+}
@@ -0,0 +1,36 @@
+// something that has to happen before a building type can be built;
+// once all of a type's requirements are met it stays unlocked
+#[derive(Clone, Debug)]
+pub enum Requirement {
+    Population(i32),
+    // at least one building of the type standing
+    Built(usize),
+    // research points gathered at buildings of the type
+    Research{ at: usize, points: i32 },
+}
+
+impl Requirement {
+    pub fn built<T>(btype: T) -> Self
+    where T: std::convert::Into<usize>
+    {
+        Requirement::Built(btype.into())
+    }
+
+    pub fn research<T>(at: T, points: i32) -> Self
+    where T: std::convert::Into<usize>
+    {
+        Requirement::Research{ at: at.into(), points }
+    }
+
+    // research is the points gathered so far at every building type
+    pub fn describe(&self, names: &[String], research: &[i32]) -> String {
+        let name = |i: usize| names.get(i).cloned().unwrap_or_else(|| format!("#{}", i));
+        match *self {
+            Requirement::Population(n) => format!("population {}", n),
+            Requirement::Built(b) => format!("a {}", name(b)),
+            Requirement::Research{ at, points } => {
+                format!("{}/{} research at a {}", research.get(at).cloned().unwrap_or(0), points, name(at))
+            },
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::game::{Resources, Stock};
+
+// how much of each good a ship carries at most, per visit
+pub const CARGO: i32 = 20;
+// how much a quota changes per key press on the trade screen
+pub const QUOTA_STEP: i32 = 10;
+
+// a city off the map that sends a ship every so often to a trade post
+#[derive(Clone)]
+pub struct Partner {
+    pub name:   String,
+    // what they charge per unit we import, 0 for goods they do not sell
+    pub sells:  Resources,
+    // what they pay per unit we export, 0 for goods they do not buy
+    pub buys:   Resources,
+    // ticks between two ships
+    pub period: i32,
+}
+
+impl Partner {
+    #[allow(dead_code)]
+    pub fn new<T>(name: &str, period: i32, num_resources: T) -> Self
+    where T: std::convert::Into<usize>
+    {
+        let n = num_resources.into();
+        Partner{
+            name:   name.to_string(),
+            sells:  Resources(vec![0; n]),
+            buys:   Resources(vec![0; n]),
+            period: period,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_sell<T>(mut self, r: T, price: i32) -> Self
+    where T: std::convert::Into<usize>
+    {
+        self.sells.set(r, price);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_buy<T>(mut self, r: T, price: i32) -> Self
+    where T: std::convert::Into<usize>
+    {
+        self.buys.set(r, price);
+        self
+    }
+}
+
+// how much of a good the city wants to keep: imports fill it up to the
+// import level, exports sell off whatever is above the export level
+#[derive(Clone, Copy, Debug)]
+pub struct Quota {
+    pub import: i32,
+    // None keeps everything
+    pub export: Option<i32>,
+}
+
+// the trade partners of the city and what it wants to trade with them;
+// everything is paid for in the currency resource
+pub struct Trade {
+    currency:     usize,
+    partners:     Vec<Partner>,
+    quotas:       Vec<Quota>,
+    // the good picked on the trade screen
+    pub selected: usize,
+}
+
+impl Trade {
+    #[allow(dead_code)]
+    pub fn new<C, T>(currency: C, num_resources: T) -> Self
+    where C: std::convert::Into<usize>, T: std::convert::Into<usize>
+    {
+        let currency = currency.into();
+        Trade{
+            currency: currency,
+            partners: vec![],
+            quotas:   vec![Quota{ import: 0, export: None }; num_resources.into()],
+            selected: if currency == 0 { 1 } else { 0 },
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_partner(mut self, partner: Partner) -> Self {
+        self.partners.push(partner);
+        self
+    }
+
+    pub fn currency(&self) -> usize {
+        self.currency
+    }
+
+    pub fn partners(&self) -> &[Partner] {
+        &self.partners
+    }
+
+    pub fn quota(&self, r: usize) -> Quota {
+        self.quotas[r]
+    }
+
+    // moves the selection on the trade screen by d goods, skipping the currency
+    pub fn select(&mut self, d: i32) {
+        let n = self.quotas.len() as i32;
+        let mut s = self.selected as i32;
+        for _ in 0..n {
+            s = (s + d).rem_euclid(n);
+            if s as usize != self.currency {
+                break
+            }
+        }
+        self.selected = s as usize;
+    }
+
+    pub fn change_import(&mut self, r: usize, d: i32) {
+        if let Some(q) = self.quotas.get_mut(r) {
+            q.import = (q.import + d).max(0);
+        }
+    }
+
+    // lowering the export level of a good we keep starts from what we have
+    pub fn change_export(&mut self, r: usize, d: i32, have: i32) {
+        if let Some(q) = self.quotas.get_mut(r) {
+            q.export = match q.export {
+                Some(level) => Some((level + d).max(0)),
+                None if d < 0 => Some(have / QUOTA_STEP * QUOTA_STEP),
+                None => None,
+            };
+        }
+    }
+
+    pub fn keep_all(&mut self, r: usize) {
+        if let Some(q) = self.quotas.get_mut(r) {
+            q.export = None;
+        }
+    }
+
+    // what a ship from the partner trades, as the change to our stock;
+    // we only buy what we can pay for and have room for
+    pub fn visit(&self, partner: &Partner, stock: &Stock) -> Resources {
+        let mut change = Resources(vec![0; stock.len()]);
+        let mut money = stock.get(self.currency);
+        for (r, q) in self.quotas.iter().enumerate() {
+            if r == self.currency {
+                continue
+            }
+            let have = stock.get(r);
+            let (sell, buy) = (partner.buys.get(r), partner.sells.get(r));
+            match q.export {
+                Some(level) if sell > 0 && have > level => {
+                    let n = (have - level).min(CARGO);
+                    change.update(r, -n);
+                    change.update(self.currency, n * sell);
+                    money += n * sell;
+                    continue
+                },
+                _ => {},
+            }
+            if buy > 0 && have < q.import {
+                let n = (q.import - have).min(CARGO).min(money / buy).min(stock.cap(r) - have);
+                if n > 0 {
+                    change.update(r, n);
+                    change.update(self.currency, -n * buy);
+                    money -= n * buy;
+                }
+            }
+        }
+        change
+    }
+}
@@ -0,0 +1,70 @@
+use crate::game::{Coord, Map};
+use crate::game::entity::Entity;
+use crate::game::path;
+use crate::game::von_neumann_neighbours;
+
+// a building sends out a walker once every this many ticks
+pub const WALKER_PERIOD: i32 = 150;
+// walkers move one tile every this many ticks
+pub const WALKER_STEP: i32 = 2;
+// how far from home a walker looks for a building to visit
+pub const WALKER_RANGE: i32 = 20;
+// buildings with a tile this close to a walker are looked after
+pub const WALKER_REACH: i32 = 2;
+// risk at which a building burns down or collapses into rubble
+pub const RISK_THRESHOLD: i32 = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalkerKind {
+    // takes away fire risk
+    Prefect,
+    // repairs damage
+    Engineer,
+}
+
+// someone walking the roads from their home building to the building
+// most at risk in range, looking after everything they pass on the way
+pub struct Walker {
+    pub kind: WalkerKind,
+    pub home: Entity,
+    pub pos:  Coord,
+    // the tiles still to walk, the next one last
+    route:    Vec<Coord>,
+}
+
+impl Walker {
+    // None when start and goal are not connected by road
+    pub fn new(kind: WalkerKind, home: Entity, map: &Map, start: Coord, goal: Coord) -> Option<Self> {
+        // routes run from the goal back to the start
+        let mut route = path::find_route(Roads(map), start, goal)?;
+        route.pop();
+        Some(Walker{ kind, home, pos: start, route })
+    }
+
+    pub fn step(&mut self) {
+        if let Some(c) = self.route.pop() {
+            self.pos = c;
+        }
+    }
+
+    pub fn arrived(&self) -> bool {
+        self.route.is_empty()
+    }
+}
+
+// the map as walkers see it: nothing but roads
+struct Roads<'a>(&'a Map);
+
+impl<'a> path::Map for Roads<'a> {
+    type Node = Coord;
+    fn neighbours(&self, n: Self::Node) -> Vec<Self::Node> {
+        von_neumann_neighbours(n).iter().cloned()
+            .filter(|p| self.0.get(p).map_or(false, |t| t.is_road())).collect()
+    }
+
+    fn g(&self, _n: Self::Node, _neighbour: Self::Node) -> i64 { 1 }
+
+    fn h(&self, n: Self::Node, goal: Self::Node) -> i64 {
+        ((goal.x - n.x).abs() + (goal.y - n.y).abs()) as i64
+    }
+}
@@ -5,27 +5,58 @@ mod game;
 
 resource_types!{Money}
 
-building_types!{House, Agora}
-
-fn update_house(resources: &mut game::Resources, i: i32) {
-    if i % 100 != 0 { 
-        return 
-    }
-    resources.update(Resource::Money, 1);
-}
-
-fn update_empty(_: &mut game::Resources, _: i32) {}
+building_types!{House, Agora, Temple, Prefecture, Workshop, Garden}
 
 fn main() {
     use Resource::*;
     use BuildingType::*;
-    use game::Game;
+    use game::{Exit, Game};
     use game::Coord;
-    use game::building::Building;
+    use game::building::{Building, Footprint};
+    use game::campaign::Campaign;
+    use game::events::{EventKind, Events};
+    use game::ledger::Category::Taxes;
+    use game::behavior::{Idle, Produce};
+    use game::scenario::{Outcome, Scenario};
+    use game::script::Scripted;
+    use game::tech::Requirement;
+    use game::walker::WalkerKind;
+
+    let agora = Scripted::new(include_str!("../../scripts/zeus/agora.rhai"), RESOURCE_NAMES, BUILDING_NAMES)
+        .expect("agora script does not compile");
 
     let buildings = [
-        Building::new_type(House, Coord::new(2,2), resources![(Money, 10)], ORANGE, update_house),
-        Building::new_type(Agora, Coord::new(3,2), resources![(Money, 200)], RED, update_empty),
+        Building::new_type(House, Coord::new(2,2), resources![(Money, 10)], ORANGE, Produce(resources![(Money, 1)]))
+            .with_income(Taxes)
+            .with_risk(1, 1)
+            .with_residents(5)
+            .with_upgrade(8, 7)
+            .with_upgrade(16, 9),
+        Building::new_type(Agora, Footprint::from_rows(&["###", "E.#"]), resources![(Money, 200)], RED, agora)
+            .with_desirability(4, 4)
+            .with_risk(1, 1)
+            .with_upkeep(resources![(Money, 3)])
+            .with_workers(3),
+        Building::new_type(Temple, Footprint::from_rows(&["###", "#E#"]), resources![(Money, 400)], YELLOW, Idle)
+            .with_requirement(Requirement::Population(60))
+            .with_prevention(EventKind::Plague, 12)
+            .with_desirability(8, 6)
+            .with_risk(0, 1)
+            .with_upkeep(resources![(Money, 4)])
+            .with_workers(2),
+        Building::new_type(Prefecture, Coord::new(2,2), resources![(Money, 40)], LIGHT_RED, Idle)
+            .with_walker(WalkerKind::Prefect)
+            .with_prevention(EventKind::Fire, 10)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(2),
+        Building::new_type(Workshop, Coord::new(2,2), resources![(Money, 40)], LIGHT_YELLOW, Idle)
+            .with_walker(WalkerKind::Engineer)
+            .with_desirability(-3, 3)
+            .with_upkeep(resources![(Money, 2)])
+            .with_workers(2),
+        Building::new_type(Garden, Coord::new(2,2), resources![(Money, 30)], LIGHT_GREEN, Idle)
+            .with_desirability(6, 4)
+            .with_upkeep(resources![(Money, 1)]),
     ];
 
     // NOTE: buildings should be in sync with enum BuildingType
@@ -35,11 +66,62 @@ fn main() {
     const MAP_WIDTH: i32 = 80;
     const MAP_HEIGHT: i32 = 45;
 
+    // every mission won unlocks more buildings for the ones after it
+    let mut campaign = Campaign::new("zeus.campaign")
+        .with_start(&[House, Prefecture, Workshop, Garden])
+        .with_mission(Scenario::new("Founding of a polis", RESOURCE_NAMES, BUILDING_NAMES)
+            .with_population(40)
+            .with_never_bankrupt(Money), &[Agora])
+        .with_mission(Scenario::new("A place to trade", RESOURCE_NAMES, BUILDING_NAMES)
+            .with_population(60)
+            .with_stockpile(Money, 2000)
+            .with_building_by(Agora, 4000)
+            .with_never_bankrupt(Money), &[Temple])
+        .with_mission(Scenario::new("Favour of the gods", RESOURCE_NAMES, BUILDING_NAMES)
+            .with_population(100)
+            .with_building_by(Temple, 6000)
+            .with_never_bankrupt(Money), &[] as &[BuildingType]);
+    // problems with the campaign file, shown in the log of the next mission
+    let mut notes = vec![];
+    if let Err(e) = campaign.load() {
+        notes.push(format!("could not load campaign progress, starting over: {}", e));
+    }
+
     let road_cost = resources![(Money, 2)];
-    let mut map = game::make_map(Coord::new(MAP_WIDTH, MAP_HEIGHT));
-    let mut game = Game::new(&buildings, MAP_WIDTH, MAP_HEIGHT, NumResources, road_cost, &mut map);
-    game.resources.set(Money, 1000);
+    let mut tcod = None;
+    while let Some(mission) = campaign.current() {
+        let map = game::make_map(Coord::new(MAP_WIDTH, MAP_HEIGHT));
+        let mut game = Game::new(&buildings, NumResources, road_cost.clone(), map)
+            .with_names(BUILDING_NAMES)
+            .with_scenario(mission.scenario.clone())
+            .with_available(&campaign.available())
+            .with_bridge_cost(resources![(Money, 10)])
+            .with_events(Events::new(776)
+                .with_chance(EventKind::Fire, 40)
+                .with_chance(EventKind::Plague, 30)
+                .with_chance(EventKind::Storm, 40)
+                .with_chance(EventKind::Windfall, 60));
+        game.resources.set(Money, 1000);
+        for note in notes.drain(..) {
+            game.log(note);
+        }
 
-    let tcod = game::get_tcod(&game);
-    game::do_loop(tcod, &mut game);
+        let tcod = tcod.get_or_insert_with(|| game::get_tcod(&game));
+        let exit = game::do_loop(tcod, &mut game);
+        // a lost mission is simply played again
+        if game.outcome() == Outcome::Victory {
+            campaign.complete();
+            if let Err(e) = campaign.save() {
+                notes.push(format!("could not save campaign progress: {}", e));
+            }
+        }
+        if exit == Exit::Quit {
+            break
+        }
+    }
+    // what no mission was left to show goes to the console once the window is gone
+    drop(tcod);
+    for note in notes {
+        eprintln!("{}", note);
+    }
 }